@@ -7,7 +7,7 @@
  * Uses:
  *   /dev/input for VirtIO-Input KEY_STATES
  *   /dev/gpu   for VirtIO-GPU framebuffer
- *   clock_gettime for timing
+ *   clock_gettime for timing, nanosleep for DG_SleepMs
  */
 
 #include "doomkeys.h"
@@ -36,6 +36,7 @@ static long syscall3(long id, long a0, long a1, long a2) {
 #define SYS_EXIT  93
 #define SYS_SCHED_YIELD 124
 #define SYS_CLOCK_GETTIME 113
+#define SYS_NANOSLEEP 101
 
 static long sys_open(const char *path, long flags) { return syscall3(SYS_OPEN, (long)path, flags, 0); }
 static long sys_read(long fd, void *buf, long len) { return syscall3(SYS_READ, fd, (long)buf, len); }
@@ -47,7 +48,9 @@ static long sys_clock_gettime(struct timespec *tp) {
     return syscall3(SYS_CLOCK_GETTIME, 1 /* CLOCK_MONOTONIC */, (long)tp, 0);
 }
 
-static void sys_yield(void) { syscall1(SYS_SCHED_YIELD, 0); }
+static long sys_nanosleep(const struct timespec *req) {
+    return syscall3(SYS_NANOSLEEP, (long)req, 0, 0);
+}
 
 /* ── Key queue ── */
 #define KEYQUEUE_SIZE 32
@@ -131,19 +134,11 @@ void DG_DrawFrame(void) {
 }
 
 void DG_SleepMs(uint32_t ms) {
-    struct timespec now;
-    sys_clock_gettime(&now);
-    long target_ns = now.tv_nsec + (long)ms * 1000000L;
-    long target_sec = now.tv_sec + target_ns / 1000000000L;
-    target_ns %= 1000000000L;
-
-    for (;;) {
-        sys_clock_gettime(&now);
-        if (now.tv_sec > target_sec ||
-            (now.tv_sec == target_sec && now.tv_nsec >= target_ns))
-            break;
-        sys_yield();
-    }
+    /* Block in the kernel sleep queue instead of spinning on sched_yield */
+    struct timespec req;
+    req.tv_sec = ms / 1000;
+    req.tv_nsec = (long)(ms % 1000) * 1000000L;
+    sys_nanosleep(&req);
 }
 
 uint32_t DG_GetTicksMs(void) {
@@ -43,6 +43,7 @@ static long _syscall3(long id, long a0, long a1, long a2) {
 #define SYS_EXIT  93
 #define SYS_SCHED_YIELD 124
 #define SYS_CLOCK_GETTIME 113
+#define SYS_NANOSLEEP 101
 
 static long sys_write(long fd, const void *buf, long len) { return _syscall3(SYS_WRITE, fd, (long)buf, len); }
 static long sys_read(long fd, void *buf, long len)        { return _syscall3(SYS_READ,  fd, (long)buf, len); }
@@ -618,9 +619,8 @@ int fileno(FILE *f) { return f ? f->fd : -1; }
 int isatty(int fd) { (void)fd; return 0; }
 int mkdir(const char *path, int mode) { (void)path; (void)mode; return -1; }
 int usleep(unsigned long us) {
-    (void)us;
-    _syscall1(SYS_SCHED_YIELD, 0);
-    return 0;
+    struct { long sec; long nsec; } req = { (long)(us / 1000000), (long)(us % 1000000) * 1000L };
+    return (int)_syscall2(SYS_NANOSLEEP, (long)&req, 0);
 }
 
 /* time — return milliseconds */
@@ -672,12 +672,16 @@ int stat(const char *path, struct stat *st) {
  * ═══════════════════════════════════════════════════════ */
 extern int main(int argc, char **argv);
 
-void _start(void) {
+void _start(long argc, char **argv) {
     /* Initialize file table */
     for (int i = 0; i < MAX_FILES; i++) _files[i].fd = -1;
 
-    /* Doom expects i_wad_dir list; pass doom1.wad as arg */
-    static char *argv[] = { "doom", "-iwad", "doom1.wad", 0 };
-    int ret = main(3, argv);
+    /* The kernel passes argc/argv in a0/a1; without arguments, default to doom1.wad */
+    static char *default_argv[] = { "doom", "-iwad", "doom1.wad", 0 };
+    if (argc <= 1) {
+        argc = 3;
+        argv = default_argv;
+    }
+    int ret = main((int)argc, argv);
     exit(ret);
 }
@@ -5,6 +5,12 @@ use tg_easy_fs::{BlockDevice, EasyFileSystem};
 const TARGET_ARCH: &str = "riscv64gc-unknown-none-elf";
 const TG_USER_VERSION: &str = "0.2.0-preview.1";
 const BLOCK_SZ: usize = 512;
+/// 未指定基址的应用构建为位置无关可执行文件（静态链接，不需要动态链接器），由内核选择加载地址
+const PIE_RUSTFLAGS: [&str; 3] = [
+    "-Crelocation-model=pie",
+    "-Clink-arg=-pie",
+    "-Clink-arg=--no-dynamic-linker",
+];
 
 #[derive(Deserialize, Default)]
 struct Cases {
@@ -16,11 +22,14 @@ struct Cases {
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-env-changed=LOG");
+    println!("cargo:rerun-if-env-changed=TIME_SLICE");
     println!("cargo:rerun-if-env-changed=TG_USER_DIR");
     println!("cargo:rerun-if-env-changed=TG_USER_VERSION");
     println!("cargo:rerun-if-env-changed=TG_SKIP_USER_APPS");
     println!("cargo:rerun-if-env-changed=CARGO_FEATURE_EXERCISE");
 
+    write_time_slice();
+
     let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
 
     // 只在 RISC-V64 架构上使用链接脚本
@@ -33,6 +42,25 @@ fn main() {
     }
 }
 
+/// 生成时间片长度常量，默认 125000 个周期（约 10 ms），可通过环境变量 `TIME_SLICE` 覆盖
+fn write_time_slice() {
+    let time_slice = match env::var("TIME_SLICE") {
+        Ok(s) => match s.parse::<u64>() {
+            Ok(value) if value > 0 => value,
+            _ => panic!("TIME_SLICE must be a positive decimal integer, got {s:?}"),
+        },
+        Err(_) => 125000,
+    };
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("time_slice.rs");
+    let source = format!(
+        "/// 时间片长度（时钟周期数），构建时可通过环境变量 `TIME_SLICE` 覆盖\n\
+         const TIME_SLICE: u64 = {time_slice};\n"
+    );
+    fs::write(&path, source).unwrap_or_else(|err| {
+        panic!("failed to write {}: {}", path.display(), err)
+    });
+}
+
 fn should_skip_build_apps() -> bool {
     if env::var_os("TG_SKIP_USER_APPS").is_some() {
         return true;
@@ -41,9 +69,19 @@ fn should_skip_build_apps() -> bool {
     is_packaged_build()
 }
 
+/// 生成链接脚本
+///
+/// 内核运行在 OpenSBI 之上（多核需要它的 HSM、IPI 扩展），由 OpenSBI 跳转到 0x80200000 处的 `_start`。
+/// 布局沿用 `NOBIOS_SCRIPT`，只把入口从 M 态的 `_m_start` 换成 `_start`；不启用 `nobios` 时 M 态区域为空。
 fn write_linker() {
     let ld = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("linker.ld");
-    fs::write(&ld, tg_linker::NOBIOS_SCRIPT).unwrap_or_else(|err| {
+    let script = String::from_utf8_lossy(AsRef::<[u8]>::as_ref(tg_linker::NOBIOS_SCRIPT));
+    assert!(
+        script.contains("ENTRY(_m_start)"),
+        "unexpected tg_linker::NOBIOS_SCRIPT entry point"
+    );
+    let script = script.replace("ENTRY(_m_start)", "ENTRY(_start)");
+    fs::write(&ld, script).unwrap_or_else(|err| {
         panic!("failed to write linker script to {}: {}", ld.display(), err)
     });
     println!("cargo:rustc-link-arg=-T{}", ld.display());
@@ -149,6 +187,8 @@ fn build_user_app(tg_user_root: &PathBuf, name: &str, base_address: u64) {
 
     if base_address != 0 {
         cmd.env("BASE_ADDRESS", base_address.to_string());
+    } else {
+        cmd.env("CARGO_ENCODED_RUSTFLAGS", PIE_RUSTFLAGS.join("\x1f"));
     }
 
     let status = cmd.status().expect("failed to execute cargo build for user app");
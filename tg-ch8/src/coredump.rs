@@ -0,0 +1,282 @@
+//! 核心转储
+//!
+//! 进程被默认动作为"终止并转储"的信号（SIGSEGV、SIGILL、SIGBUS 等）终止时，
+//! `processor::kill_current` 调用 [`dump`] 把进程的状态写入 easy-fs 中的 `core.<pid>`。
+//! 文件是标准的 ELF core 文件，可以在宿主机上用 `riscv64-elf-gdb <程序> core.<pid>` 查看：
+//!
+//! ```text
+//! ┌──────────────┐
+//! │   ELF 头     │  e_type = ET_CORE
+//! ├──────────────┤
+//! │   程序头表   │  1 个 PT_NOTE + 每段权限相同的连续用户页 1 个 PT_LOAD
+//! ├──────────────┤
+//! │   PT_NOTE    │  每个线程一条 NT_PRSTATUS（pc 与 x1–x31），出错的线程在最前
+//! ├──────────────┤ ← 按页对齐
+//! │ PT_LOAD ...  │  用户页的内容，权限取自页表
+//! └──────────────┘
+//! ```
+//!
+//! 正在其他核上运行的线程，记录的是它最近一次陷入内核时保存的寄存器。
+//! 文件大小超过 `RLIMIT_CORE` 的软限制时不转储。
+
+use crate::{
+    build_flags, cow,
+    fs::FS,
+    proc_tree::PROC_TREE,
+    process::{CpuUsage, Process},
+    processor::ProcessorInner,
+    rlimit::RLIMIT_CORE,
+    timer::CLOCK_FREQ,
+    Sv39, PROCESSOR,
+};
+use alloc::vec::Vec;
+use tg_easy_fs::{FSManager, OpenFlags};
+use tg_kernel_context::LocalContext;
+use tg_kernel_vm::page_table::{VmFlags, VPN};
+use tg_signal::SignalNo;
+use tg_task_manage::{ProcId, ThreadId};
+
+const PAGE_SIZE: usize = 4096;
+
+/// ELF 头的大小
+const EHDR_SIZE: usize = 64;
+/// 程序头的大小
+const PHDR_SIZE: usize = 56;
+/// 注记的名字 "CORE"，含结尾的 0 补齐到 4 字节对齐
+const NOTE_NAME: &[u8; 8] = b"CORE\0\0\0\0";
+/// `struct elf_prstatus` 的大小（riscv64 Linux）
+const PRSTATUS_SIZE: usize = 376;
+/// 一条 NT_PRSTATUS 注记的大小：namesz、descsz、type 三个字段 + 名字 + 内容
+const NOTE_SIZE: usize = 12 + NOTE_NAME.len() + PRSTATUS_SIZE;
+
+const ET_CORE: usize = 4;
+const EM_RISCV: usize = 243;
+/// 与用户程序一致：RVC 扩展、双精度浮点 ABI
+const EF_RISCV: usize = 0x5;
+const PT_LOAD: usize = 1;
+const PT_NOTE: usize = 4;
+const NT_PRSTATUS: usize = 1;
+const PF_X: usize = 1;
+const PF_W: usize = 2;
+const PF_R: usize = 4;
+
+/// 默认动作为终止并转储的信号
+const DUMP_SIGNALS: [SignalNo; 10] = [
+    SignalNo::SIGQUIT,
+    SignalNo::SIGILL,
+    SignalNo::SIGTRAP,
+    SignalNo::SIGABRT,
+    SignalNo::SIGBUS,
+    SignalNo::SIGFPE,
+    SignalNo::SIGSEGV,
+    SignalNo::SIGXCPU,
+    SignalNo::SIGXFSZ,
+    SignalNo::SIGSYS,
+];
+
+/// 被信号 `signo` 终止的进程是否应当转储
+pub fn dumps(signo: usize) -> bool {
+    DUMP_SIGNALS.iter().any(|&signal| signal as usize == signo)
+}
+
+/// 一段权限相同的连续用户页
+struct Segment {
+    /// 起始页号
+    start: usize,
+    /// 结束页号（不含）
+    end: usize,
+    /// ELF 段权限（`PF_*`）
+    flags: usize,
+}
+
+/// 把当前进程（被信号 `signo` 终止）转储到 `core.<pid>`，成功时返回 `true`
+pub fn dump(signo: usize) -> bool {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let current = unsafe { (*processor).current().unwrap().tid };
+    let process = unsafe { (*processor).get_current_proc().unwrap() };
+    let pid = process.pid;
+
+    // 出错的线程在最前：gdb 把第一条 NT_PRSTATUS 当作当前线程
+    let mut tids = vec![current];
+    if let Some(threads) = unsafe { (*processor).get_thread(pid) } {
+        tids.extend(threads.iter().copied().filter(|&tid| tid != current));
+    }
+    let ids = {
+        let tree = PROC_TREE.lock();
+        let id = |pid: Option<ProcId>| pid.map_or(0, |pid| pid.get_usize());
+        [id(tree.parent(pid)), id(tree.pgid(pid)), id(tree.sid(pid))]
+    };
+    let mut notes = Vec::with_capacity(tids.len() * NOTE_SIZE);
+    for tid in tids {
+        let thread = unsafe { (*processor).get_task(tid).unwrap() };
+        prstatus(&mut notes, signo, tid, ids, &thread.context.context, &thread.usage);
+    }
+
+    let segments = segments(process);
+    let phnum = 1 + segments.len();
+    let notes_offset = EHDR_SIZE + phnum * PHDR_SIZE;
+    let data_offset = (notes_offset + notes.len()).next_multiple_of(PAGE_SIZE);
+    let pages: usize = segments.iter().map(|seg| seg.end - seg.start).sum();
+    if data_offset + pages * PAGE_SIZE > process.rlimits.get(RLIMIT_CORE).unwrap().cur {
+        return false;
+    }
+
+    let mut head = Vec::with_capacity(data_offset);
+    ehdr(&mut head, phnum);
+    phdr(&mut head, PT_NOTE, 0, notes_offset, 0, notes.len(), 4);
+    let mut offset = data_offset;
+    for seg in &segments {
+        let len = (seg.end - seg.start) * PAGE_SIZE;
+        let vaddr = VPN::<Sv39>::new(seg.start).base().val();
+        phdr(&mut head, PT_LOAD, seg.flags, offset, vaddr, len, PAGE_SIZE);
+        offset += len;
+    }
+    head.extend_from_slice(&notes);
+    head.resize(data_offset, 0);
+
+    let name = format!("core.{}", pid.get_usize());
+    let Some(inode) = FS
+        .open(&name, OpenFlags::CREATE | OpenFlags::WRONLY)
+        .and_then(|file| file.inode.clone())
+    else {
+        log::warn!("failed to create {name}");
+        return false;
+    };
+    inode.write_at(0, &head);
+    let mut offset = data_offset;
+    for vpn in segments.iter().flat_map(|seg| seg.start..seg.end) {
+        let page = process
+            .address_space
+            .translate::<[u8; PAGE_SIZE]>(VPN::<Sv39>::new(vpn).base(), build_flags("U___V"))
+            .unwrap();
+        inode.write_at(offset, unsafe { page.as_ref() });
+        offset += PAGE_SIZE;
+    }
+    log::info!("core dumped to {name}");
+    true
+}
+
+/// 进程的用户页，按页号排序并合并为权限相同的连续段
+fn segments(process: &Process) -> Vec<Segment> {
+    let mut vpns: Vec<usize> = process
+        .address_space
+        .areas
+        .iter()
+        .flat_map(|range| range.start.val()..range.end.val())
+        .collect();
+    vpns.sort_unstable();
+    let mut segments: Vec<Segment> = Vec::new();
+    for vpn in vpns {
+        let Some(flags) = page_flags(process, vpn) else {
+            continue;
+        };
+        match segments.last_mut() {
+            Some(seg) if seg.end == vpn && seg.flags == flags => seg.end += 1,
+            _ => segments.push(Segment { start: vpn, end: vpn + 1, flags }),
+        }
+    }
+    segments
+}
+
+/// 页 `vpn` 的 ELF 段权限，不是用户页时返回 `None`
+fn page_flags(process: &Process, vpn: usize) -> Option<usize> {
+    let addr = VPN::<Sv39>::new(vpn).base();
+    let has = |flags: VmFlags<Sv39>| process.address_space.translate::<u8>(addr, flags).is_some();
+    if !has(build_flags("U___V")) {
+        return None;
+    }
+    let mut flags = 0;
+    let perms = [(build_flags("RV"), PF_R), (build_flags("W_V"), PF_W), (build_flags("X__V"), PF_X)];
+    for (vm_flags, pf) in perms {
+        if has(vm_flags) {
+            flags |= pf;
+        }
+    }
+    // 尚未复制的 COW 页对进程而言仍然可写
+    if cow::is_cow(&process.address_space, vpn) {
+        flags |= PF_W;
+    }
+    Some(flags)
+}
+
+/// 按小端序追加 `value` 的低 `size` 字节
+fn le(out: &mut Vec<u8>, value: usize, size: usize) {
+    out.extend_from_slice(&value.to_le_bytes()[..size]);
+}
+
+/// ELF 头
+fn ehdr(out: &mut Vec<u8>, phnum: usize) {
+    // 64 位、小端、版本 1、System V ABI
+    out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    le(out, ET_CORE, 2);
+    le(out, EM_RISCV, 2);
+    le(out, 1, 4); // e_version
+    le(out, 0, 8); // e_entry
+    le(out, EHDR_SIZE, 8); // e_phoff
+    le(out, 0, 8); // e_shoff
+    le(out, EF_RISCV, 4);
+    le(out, EHDR_SIZE, 2);
+    le(out, PHDR_SIZE, 2);
+    le(out, phnum, 2);
+    le(out, 0, 2); // e_shentsize
+    le(out, 0, 2); // e_shnum
+    le(out, 0, 2); // e_shstrndx
+}
+
+/// 程序头（文件内与内存中的大小相同）
+fn phdr(
+    out: &mut Vec<u8>,
+    ty: usize,
+    flags: usize,
+    offset: usize,
+    vaddr: usize,
+    size: usize,
+    align: usize,
+) {
+    le(out, ty, 4);
+    le(out, flags, 4);
+    le(out, offset, 8);
+    le(out, vaddr, 8);
+    le(out, 0, 8); // p_paddr
+    le(out, size, 8); // p_filesz
+    le(out, size, 8); // p_memsz
+    le(out, align, 8);
+}
+
+/// 线程 `tid` 的 NT_PRSTATUS 注记，`ids` 依次为进程的 ppid、pgid、sid
+fn prstatus(
+    out: &mut Vec<u8>,
+    signo: usize,
+    tid: ThreadId,
+    ids: [usize; 3],
+    ctx: &LocalContext,
+    usage: &CpuUsage,
+) {
+    le(out, NOTE_NAME.len() - 3, 4); // namesz：含结尾 0 的 "CORE"
+    le(out, PRSTATUS_SIZE, 4);
+    le(out, NT_PRSTATUS, 4);
+    out.extend_from_slice(NOTE_NAME);
+
+    let start = out.len();
+    le(out, signo, 4); // pr_info.si_signo
+    le(out, 0, 8); // pr_info.si_code、si_errno
+    le(out, signo, 2); // pr_cursig
+    out.resize(out.len() + 2 + 8 + 8, 0); // 对齐、pr_sigpend、pr_sighold
+    // gdb 以 pr_pid 作为线程号，这里填 TID
+    le(out, tid.get_usize(), 4);
+    for id in ids {
+        le(out, id, 4);
+    }
+    for ticks in [usage.utime, usage.stime, 0, 0] {
+        let ticks = ticks as usize;
+        let freq = CLOCK_FREQ as usize;
+        le(out, ticks / freq, 8);
+        le(out, ticks % freq * 1_000_000 / freq, 8);
+    }
+    // pr_reg：pc 在 x0 的位置
+    le(out, ctx.pc(), 8);
+    for i in 1..32 {
+        le(out, ctx.x(i), 8);
+    }
+    out.resize(start + PRSTATUS_SIZE, 0); // pr_fpvalid 与结尾对齐
+}
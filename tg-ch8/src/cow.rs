@@ -0,0 +1,136 @@
+//! 写时复制（copy-on-write）的 fork
+//!
+//! fork 不再逐页复制父进程的地址空间，而是让子进程的页表指向同一批物理页（见 [`share`]）：
+//! 可写的页在父子双方都去掉 W 位并打上 [`PTE_COW`] 标记，第一次写入时在缺页异常中复制（见 [`fault`]）。
+//! 一个物理页被几个地址空间共享由 `Sv39Manager` 的引用计数记录，最后一个使用者释放时才归还。
+//!
+//! - 共享者只剩自己的 COW 页不必复制，直接恢复 W 位；
+//! - mmap 的共享文件映射（`MAP_SHARED`）不做写时复制：子进程映射同一物理页，保留原有权限，父子双方的写入互相可见；
+//! - 内核代替进程写用户内存（如系统调用的输出参数）前同样要解除写时复制，见 `Process::translate_mut`；
+//! - 父进程的其他线程可能正在别的核上运行并缓存着可写的旧表项，fork 后由调用者让那些核刷新 TLB。
+
+use crate::{mmap::MemoryMap, Sv39, Sv39Manager};
+use alloc::alloc::alloc;
+use core::alloc::Layout;
+use tg_kernel_vm::{
+    page_table::{MmuMeta, VmFlags, PPN, VPN},
+    AddressSpace,
+};
+
+const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+
+/// 页表项的 V 位
+const PTE_V: usize = 1 << 0;
+/// 页表项的 R 位
+const PTE_R: usize = 1 << 1;
+/// 页表项的 W 位
+const PTE_W: usize = 1 << 2;
+/// 页表项的 X 位
+const PTE_X: usize = 1 << 3;
+/// 页表项的 U 位
+const PTE_U: usize = 1 << 4;
+/// 页表项中由硬件解释的标志位（V、R、W、X、U、G、A、D）
+const PTE_HW_FLAGS: usize = 0xff;
+/// 写时复制标记：页表项中软件保留的第 9 位（第 8 位是 `Sv39Manager` 的 OWNED 标记）
+const PTE_COW: usize = 1 << 9;
+/// 页表项中物理页号的起始位
+const PPN_SHIFT: usize = 10;
+/// 每级页表的页号位数
+const LEVEL_BITS: usize = 9;
+
+/// fork：让子进程的地址空间 `child` 共享 `parent` 的全部用户页
+///
+/// 可写的页在双方都变为只读的 COW 页，其余的页（代码段、只读数据）直接共享。
+/// 属于共享文件映射的页（`mmap` 判定）保持原有权限，父子双方写的是同一物理页。
+pub fn share(
+    parent: &AddressSpace<Sv39, Sv39Manager>,
+    child: &mut AddressSpace<Sv39, Sv39Manager>,
+    mmap: &MemoryMap,
+) {
+    for range in &parent.areas {
+        for vpn in range.start.val()..range.end.val() {
+            let Some(pte) = leaf(parent, vpn) else {
+                continue;
+            };
+            let pte = unsafe { &mut *pte };
+            if *pte & PTE_W != 0 && !mmap.is_shared(vpn) {
+                *pte = (*pte & !PTE_W) | PTE_COW;
+            }
+            let ppn = PPN::new(*pte >> PPN_SHIFT);
+            Sv39Manager::share(ppn);
+            let flags = unsafe { VmFlags::from_raw(*pte & ((1 << PPN_SHIFT) - 1)) };
+            let vpn = VPN::new(vpn);
+            child.map_extern(vpn..vpn + 1, ppn, flags);
+        }
+    }
+}
+
+/// 处理写页 `vpn` 产生的缺页异常，能够处理时返回 `true`（出错的指令可以重新执行）
+///
+/// COW 页仍被其他地址空间共享时复制一份，否则直接恢复写权限；不是 COW 页或内存不足、
+/// 无法复制时返回 `false`，表项保持不变。
+pub fn fault(space: &mut AddressSpace<Sv39, Sv39Manager>, vpn: usize) -> bool {
+    let Some(pte) = leaf(space, vpn) else {
+        return false;
+    };
+    let pte = unsafe { &mut *pte };
+    if *pte & (PTE_U | PTE_W) == PTE_U | PTE_W {
+        // 同一进程的另一个线程已经处理过这一页，本核缓存的是旧的只读表项
+        return true;
+    }
+    if *pte & PTE_COW == 0 {
+        return false;
+    }
+    let ppn = PPN::<Sv39>::new(*pte >> PPN_SHIFT);
+    if Sv39Manager::is_shared(ppn) {
+        let layout = unsafe { Layout::from_size_align_unchecked(PAGE_SIZE, PAGE_SIZE) };
+        let frame = unsafe { alloc(layout) };
+        if frame.is_null() {
+            return false;
+        }
+        let old = VPN::<Sv39>::new(ppn.val()).base().as_mut_ptr::<u8>();
+        unsafe { core::ptr::copy_nonoverlapping(old, frame, PAGE_SIZE) };
+        Sv39Manager::release(ppn, 1);
+        let flags = *pte & ((1 << PPN_SHIFT) - 1);
+        *pte = ((frame as usize >> Sv39::PAGE_BITS) << PPN_SHIFT) | flags;
+    }
+    *pte = (*pte & !PTE_COW) | PTE_W;
+    true
+}
+
+/// 原地把已映射的页 `vpn` 的权限改为 `flags`，保留物理页号和软件标记（OWNED、COW）
+///
+/// 供 mmap 修改共享映射的页：这些页可能与其他地址空间共用，不能复制后重新映射。
+pub fn set_flags(space: &AddressSpace<Sv39, Sv39Manager>, vpn: usize, flags: VmFlags<Sv39>) {
+    let Some(pte) = leaf(space, vpn) else {
+        return;
+    };
+    let pte = unsafe { &mut *pte };
+    *pte = (*pte & !PTE_HW_FLAGS) | (flags.val() & PTE_HW_FLAGS);
+}
+
+/// 页 `vpn` 是否为尚未解除写时复制的页（对进程而言可写）
+pub fn is_cow(space: &AddressSpace<Sv39, Sv39Manager>, vpn: usize) -> bool {
+    leaf(space, vpn).is_some_and(|pte| unsafe { *pte } & PTE_COW != 0)
+}
+
+/// 页 `vpn` 的叶子页表项，页未映射时返回 `None`
+///
+/// 用户页都是 4 KiB 的页，大页映射的地址也返回 `None`。
+fn leaf(space: &AddressSpace<Sv39, Sv39Manager>, vpn: usize) -> Option<*mut usize> {
+    // 内核恒等映射物理内存，页表所在的物理页可以直接访问
+    let mut table = space.root_ppn().val() << Sv39::PAGE_BITS;
+    for level in (0..=Sv39::MAX_LEVEL).rev() {
+        let index = (vpn >> (LEVEL_BITS * level)) & ((1 << LEVEL_BITS) - 1);
+        let pte = unsafe { (table as *mut usize).add(index) };
+        let value = unsafe { *pte };
+        if value & PTE_V == 0 {
+            return None;
+        }
+        if value & (PTE_R | PTE_W | PTE_X) != 0 {
+            return (level == 0).then_some(pte);
+        }
+        table = (value >> PPN_SHIFT) << Sv39::PAGE_BITS;
+    }
+    None
+}
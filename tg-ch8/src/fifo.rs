@@ -0,0 +1,64 @@
+//! 先来先服务（FIFO）调度器
+//!
+//! 早先的轮转调度：就绪线程按入队顺序依次运行，不区分优先级，也不根据线程的行为调整位置。
+//! 作为 `processor::ThreadManager` 的另一种 `Policy`，通过 `fifo` feature 启用，
+//! 用作比较 stride、MLFQ 调度效果的基准：
+//!
+//! ```bash
+//! cargo run --features fifo
+//! ```
+
+use crate::process::Thread;
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+};
+use tg_task_manage::{Manage, Schedule, ThreadId};
+
+/// FIFO 调度策略
+///
+/// 维护所有线程实体和一个就绪队列。
+pub struct Fifo {
+    /// 线程实体表（TID → Thread）
+    tasks: BTreeMap<ThreadId, Box<Thread>>,
+    /// 就绪队列
+    ready_queue: VecDeque<ThreadId>,
+}
+
+impl Fifo {
+    /// 创建空的调度策略
+    pub fn new() -> Self {
+        Self { tasks: BTreeMap::new(), ready_queue: VecDeque::new() }
+    }
+}
+
+impl Manage<Thread, ThreadId> for Fifo {
+    /// 插入线程实体
+    #[inline]
+    fn insert(&mut self, id: ThreadId, task: Thread) {
+        self.tasks.insert(id, Box::new(task));
+    }
+    /// 获取线程可变引用
+    #[inline]
+    fn get_mut(&mut self, id: ThreadId) -> Option<&mut Thread> {
+        self.tasks.get_mut(&id).map(|t| &mut **t)
+    }
+    /// 删除线程实体
+    #[inline]
+    fn delete(&mut self, id: ThreadId) { self.tasks.remove(&id); }
+}
+
+impl Schedule<ThreadId> for Fifo {
+    /// 加入就绪队列队尾
+    fn add(&mut self, id: ThreadId) { self.ready_queue.push_back(id); }
+    /// 取出队首线程
+    fn fetch(&mut self) -> Option<ThreadId> {
+        // 跳过已退出线程留下的 TID
+        while let Some(id) = self.ready_queue.pop_front() {
+            if self.tasks.contains_key(&id) {
+                return Some(id);
+            }
+        }
+        None
+    }
+}
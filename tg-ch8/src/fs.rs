@@ -2,7 +2,7 @@
 //!
 //! 本模块与第七章相同，提供：
 //! - `FS`：全局文件系统实例（easy-fs 根 Inode）
-//! - `Fd`：统一文件描述符枚举（File / PipeRead / PipeWrite / Empty）
+//! - `Fd`：统一文件描述符枚举（File / PipeRead / PipeWrite / Console）
 //! - `read_all`：读取文件全部内容的辅助函数
 //!
 //! 在第八章中，文件描述符表 `fd_table` 属于 `Process`（进程），
@@ -97,8 +97,8 @@ pub enum Fd {
     PipeRead(PipeReader),
     /// 管道写端（只写）
     PipeWrite(Arc<PipeWriter>),
-    /// 空描述符（用于 stdin/stdout/stderr）
-    Empty {
+    /// 控制台（进程初始的 stdin/stdout/stderr），可以像其他描述符一样被 dup、关闭或替换
+    Console {
         /// 是否可读
         read: bool,
         /// 是否可写
@@ -117,7 +117,7 @@ impl Fd {
             Fd::File(f) => f.readable(),
             Fd::PipeRead(_) => true,
             Fd::PipeWrite(_) => false,
-            Fd::Empty { read, .. } => *read,
+            Fd::Console { read, .. } => *read,
             Fd::VirtioGpu => false, // GPU is typically write-only via this interface
             Fd::VirtioInput => true,
         }
@@ -129,7 +129,7 @@ impl Fd {
             Fd::File(f) => f.writable(),
             Fd::PipeRead(_) => false,
             Fd::PipeWrite(_) => true,
-            Fd::Empty { write, .. } => *write,
+            Fd::Console { write, .. } => *write,
             Fd::VirtioGpu => true,
             Fd::VirtioInput => false,
         }
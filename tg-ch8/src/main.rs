@@ -47,12 +47,41 @@
 
 #![cfg_attr(not(target_arch = "riscv64"), allow(dead_code, unused_imports))]
 
+/// 核心转储：进程被致命信号终止时写出 ELF core 文件
+mod coredump;
+/// 写时复制：fork 共享物理页，第一次写入时复制
+mod cow;
 /// 文件系统模块：easy-fs 封装 + 统一 Fd 枚举
 mod fs;
 /// 进程与线程模块：Process（资源容器）和 Thread（执行单元）
 mod process;
 /// 处理器模块：PROCESSOR 全局管理器（PThreadManager）
 mod processor;
+/// 进程树：父子关系、孤儿过继与僵尸进程
+mod proc_tree;
+/// 进程资源限制：getrlimit / setrlimit 与各处的限额检查
+mod rlimit;
+/// 内存映射：mmap / munmap / mprotect / msync、进程的映射区域表与文件映射的缺页处理
+mod mmap;
+/// 多级反馈队列调度器（启用 `mlfq` feature 时替换 stride 调度）
+#[cfg(feature = "mlfq")]
+mod mlfq;
+/// 先来先服务调度器（启用 `fifo` feature 时替换 stride 调度）
+#[cfg(feature = "fifo")]
+mod fifo;
+
+#[cfg(all(feature = "mlfq", feature = "fifo"))]
+compile_error!("features `mlfq` and `fifo` select different schedulers and cannot be enabled together");
+/// 多核支持：核启动、大内核锁、每核状态、IPI 与远程 TLB 刷新
+mod smp;
+/// 扩展系统调用：tg-syscall 未定义的系统调用（nanosleep 等）
+mod syscall_ext;
+/// 线程栈分配器：带保护页的线程用户栈，线程回收时释放
+mod thread_stack;
+/// 时钟与睡眠队列：nanosleep 的定时唤醒
+mod timer;
+/// 控制台终端：输入缓冲、前台进程组与 Ctrl-C / Ctrl-Z
+mod tty;
 /// VirtIO 块设备驱动
 mod virtio_block;
 
@@ -65,10 +94,11 @@ extern crate alloc;
 use crate::{
     fs::{read_all, FS},
     impls::{Sv39Manager, SyscallContext},
-    process::{Process, Thread},
+    proc_tree::PROC_TREE,
+    process::{CpuUsage, Process, SwitchReason, Thread},
     processor::{ProcManager, ProcessorInner, ThreadManager},
 };
-use alloc::alloc::alloc;
+use alloc::{alloc::alloc, string::String};
 use core::{alloc::GlobalAlloc, alloc::Layout, cell::UnsafeCell, mem::MaybeUninit, ptr::NonNull};
 use customizable_buddy::{BuddyAllocator, LinkedListBuddy, UsizeBuddy};
 use spin::Mutex;
@@ -104,13 +134,14 @@ unsafe impl GlobalAlloc for LockedHeap {
 static ALLOCATOR: LockedHeap = LockedHeap::new();
 
 use impls::Console;
-pub use processor::PROCESSOR;
+pub use processor::{INITPROC, PROCESSOR};
 use riscv::register::*;
 #[cfg(not(target_arch = "riscv64"))]
 use stub::Sv39;
 use tg_console::log;
 use tg_easy_fs::{FSManager, OpenFlags};
-use tg_kernel_context::foreign::MultislotPortal;
+use smp::HARTS;
+use tg_kernel_context::foreign::{ForeignContext, MultislotPortal};
 #[cfg(target_arch = "riscv64")]
 use tg_kernel_vm::page_table::Sv39;
 use tg_kernel_vm::{
@@ -118,9 +149,9 @@ use tg_kernel_vm::{
     AddressSpace,
 };
 use tg_sbi;
-use tg_signal::SignalResult;
+use tg_signal::{SignalNo, SignalResult};
 use tg_syscall::Caller;
-use tg_task_manage::ProcId;
+use tg_task_manage::{ProcId, ThreadId};
 use xmas_elf::ElfFile;
 
 // ─── VirtIO-GPU / VirtIO-Input ───
@@ -156,7 +187,9 @@ fn parse_flags(s: &str) -> Result<VmFlags<Sv39>, ()> {
 #[cfg(not(target_arch = "riscv64"))]
 use stub::{build_flags, parse_flags};
 
-// 内核入口，栈 = 32 页 = 128 KiB。
+// 内核入口，栈 = 32 页 = 128 KiB（引导核使用 0 号核栈）。
+//
+// 只有第一个到达的核成为引导核，之后直接进入这里的核停在 `wfi` 循环中（见 `smp::BOOT_CLAIMED`）。
 //
 // 这里不再调用 tg_linker::boot0! 宏，避免外部已发布版本与 Rust 2024
 // 在属性语义上的兼容差异影响本 crate 的发布校验。
@@ -165,15 +198,18 @@ use stub::{build_flags, parse_flags};
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".text.entry")]
 unsafe extern "C" fn _start() -> ! {
-    const STACK_SIZE: usize = 32 * 4096;
-    #[unsafe(link_section = ".boot.stack")]
-    static mut STACK: [u8; STACK_SIZE] = [0u8; STACK_SIZE];
-
     core::arch::naked_asm!(
+        "la   t0, {claimed}",
+        "li   t1, 1",
+        "amoswap.w.aq t1, t1, (t0)",
+        "bnez t1, 1f",
         "la sp, {stack} + {stack_size}",
         "j  {main}",
-        stack = sym STACK,
-        stack_size = const STACK_SIZE,
+        "1: wfi",
+        "j  1b",
+        claimed = sym smp::BOOT_CLAIMED,
+        stack = sym smp::STACKS,
+        stack_size = const smp::STACK_SIZE,
         main = sym rust_main,
     )
 }
@@ -185,6 +221,8 @@ const MEMORY: usize = 256 << 20;
 static mut HEAP_META: [u8; 4 * 1024 * 1024] = [1u8; 4 * 1024 * 1024];
 /// 异界传送门所在虚页
 const PROTAL_TRANSIT: VPN<Sv39> = VPN::MAX;
+// 时间片长度 `TIME_SLICE`（时钟周期数）由 build.rs 根据环境变量生成
+include!(concat!(env!("OUT_DIR"), "/time_slice.rs"));
 
 /// 内核地址空间的全局存储
 struct KernelSpace {
@@ -223,7 +261,10 @@ pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x00_8000)];
 /// - 使用 `PThreadManager`（双层管理器）替代 `PManager`
 /// - 初始化时同时创建 Process 和 Thread
 /// - 主循环中新增**线程阻塞**处理（SEMAPHORE_DOWN/MUTEX_LOCK/CONDVAR_WAIT）
-extern "C" fn rust_main() -> ! {
+/// - 主循环使用时钟中断实现**抢占式**线程调度（时间片长度见 `TIME_SLICE`）
+/// - 初始化完成后唤醒其余核，所有核共享就绪队列（见 `smp` 模块）
+/// - `nanosleep` 让线程阻塞在按唤醒时刻排序的睡眠队列中（见 `timer` 模块），可被信号提前唤醒
+extern "C" fn rust_main(hart_id: usize) -> ! {
     let layout = tg_linker::KernelLayout::locate();
     // 步骤 1：BSS 清零
     unsafe { layout.zero_bss() };
@@ -241,15 +282,15 @@ extern "C" fn rust_main() -> ! {
             200 * 1024 * 1024,
         )
     };
-    // 步骤 4：异界传送门
-    let portal_size = MultislotPortal::calculate_size(1);
+    // 步骤 4：异界传送门（每个核一个 slot）
+    let portal_size = MultislotPortal::calculate_size(smp::MAX_HARTS);
     let portal_layout = Layout::from_size_align(portal_size, 1 << Sv39::PAGE_BITS).unwrap();
     let portal_ptr = unsafe { alloc(portal_layout) };
     assert!(portal_layout.size() < 1 << Sv39::PAGE_BITS);
     // 步骤 5：内核地址空间
     kernel_space(layout, MEMORY, portal_ptr as _);
     // 步骤 6：异界传送门初始化
-    let portal = unsafe { MultislotPortal::init_transit(PROTAL_TRANSIT.base().val(), 1) };
+    let portal = unsafe { MultislotPortal::init_transit(PROTAL_TRANSIT.base().val(), smp::MAX_HARTS) };
     // 步骤 7：系统调用初始化
     tg_syscall::init_io(&SyscallContext);
     tg_syscall::init_process(&SyscallContext);
@@ -303,21 +344,85 @@ extern "C" fn rust_main() -> ! {
     println!("[DEBUG] Reading initproc...");
     let initproc = read_all(initproc_file);
     println!("[DEBUG] initproc read (size={}), loading ELF...", initproc.len());
-    if let Some((process, thread)) = Process::from_elf(ElfFile::new(initproc.as_slice()).unwrap()) {
+    let argv = [String::from("initproc")];
+    if let Some((process, thread)) = Process::from_elf(ElfFile::new(initproc.as_slice()).unwrap(), &argv, &[]) {
         // 初始化双层管理器：ProcManager（进程）+ ThreadManager（线程）
         PROCESSOR.get_mut().set_proc_manager(ProcManager::new());
         PROCESSOR.get_mut().set_manager(ThreadManager::new());
         let (pid, tid) = (process.pid, thread.tid);
+        INITPROC.call_once(|| pid);
+        PROC_TREE.lock().insert(pid, ProcId::from_usize(usize::MAX));
         PROCESSOR
             .get_mut()
             .add_proc(pid, process, ProcId::from_usize(usize::MAX));
         PROCESSOR.get_mut().add(tid, thread, pid);
+        // 控制台属于 initproc 的会话，shell 再为每个作业切换前台进程组
+        tty::attach(pid);
+    }
+
+    // 步骤 9：唤醒其余核，随后引导核也进入调度循环
+    smp::set_portal(portal);
+    #[cfg(target_arch = "riscv64")]
+    smp::start_secondary_harts(hart_id, _secondary_start as usize);
+    let _ = hart_id;
+    schedule(0)
+}
+
+/// 从核入口：按 `opaque`（a1，逻辑核号）选择内核栈
+#[cfg(target_arch = "riscv64")]
+#[unsafe(naked)]
+unsafe extern "C" fn _secondary_start(hart_id: usize, cpu: usize) -> ! {
+    core::arch::naked_asm!(
+        "addi t0, a1, 1",
+        "li   t1, {stack_size}",
+        "mul  t0, t0, t1",
+        "la   sp, {stack}",
+        "add  sp, sp, t0",
+        "j    {main}",
+        stack = sym smp::STACKS,
+        stack_size = const smp::STACK_SIZE,
+        main = sym secondary_main,
+    )
+}
+
+/// 从核主函数：切换到内核地址空间后进入调度循环
+#[cfg(target_arch = "riscv64")]
+extern "C" fn secondary_main(_hart_id: usize, cpu: usize) -> ! {
+    unsafe {
+        satp::set(satp::Mode::Sv39, 0, KERNEL_SPACE.assume_init_ref().root_ppn().val());
+        core::arch::asm!("sfence.vma");
     }
+    schedule(cpu)
+}
+
+/// 调度循环（每个核各运行一份）
+///
+/// 只在执行用户代码期间释放大内核锁：取线程、处理 Trap 都在锁内完成，
+/// 因此系统调用实现看到的 `PROCESSOR.current()` 总是本核的线程。
+///
+/// 没有可运行线程时，若 initproc 仍存活（其余线程只是阻塞），本核执行 `wfi` 空闲等待；
+/// initproc 退出后才关机。
+fn schedule(cpu: usize) -> ! {
+    // 开启 S 特权级时钟中断（抢占）与软件中断（IPI 唤醒）。
+    // 不开启 sstatus::SIE，内核态执行期间不响应中断，只在 U 态被打断。
+    smp::enable_interrupts();
+    let portal = smp::portal();
 
     // ─── 主调度循环 ───
     loop {
+        let guard = smp::lock(cpu);
+        HARTS.get_mut(cpu).idle = false;
         let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
 
+        // 唤醒睡眠到期的线程；本核只能运行其中一个，其余交给空闲核
+        if timer::wake_expired(unsafe { &mut *processor }) > 1 {
+            HARTS.kick_idle();
+        }
+        // 读取控制台输入：唤醒等待输入的线程，Ctrl-C / Ctrl-Z 向前台进程组发送信号
+        if tty::poll(unsafe { &mut *processor }) > 1 {
+            HARTS.kick_idle();
+        }
+
         // ── Poll VirtIO-Input for key states ──
         unsafe {
             for i in 0..2 {
@@ -331,72 +436,261 @@ extern "C" fn rust_main() -> ! {
             }
         }
 
+        let Some(task) = (unsafe { (*processor).find_next() }) else {
+            // 只有 initproc 退出（且没有核在运行线程）时才关机
+            let initproc_exited = INITPROC
+                .get()
+                .map_or(true, |&pid| unsafe { (*processor).get_proc(pid) }.is_none());
+            if initproc_exited && !HARTS.any_running() {
+                println!("no task");
+                break;
+            }
+            // 线程都在阻塞或在其他核上运行：空闲等待。
+            // 有线程就绪时会收到 IPI；同时设置一次时钟中断，定期醒来轮询输入设备，
+            // 或在最早的睡眠线程到期时醒来唤醒它。
+            HARTS.get_mut(cpu).idle = true;
+            timer::program(timer::now() + TIME_SLICE);
+            drop(guard);
+            smp::wait_for_interrupt();
+            continue;
+        };
 
-        if let Some(task) = unsafe { (*processor).find_next() } {
-            unsafe { task.context.execute(portal, ()) };
-
-            match scause::read().cause() {
-                // ─── 系统调用 ───
-                scause::Trap::Exception(scause::Exception::UserEnvCall) => {
-                    use tg_syscall::{SyscallId as Id, SyscallResult as Ret};
-                    let ctx = &mut task.context.context;
-                    ctx.move_next();
-                    let id: Id = ctx.a(7).into();
-                    let args = [ctx.a(0), ctx.a(1), ctx.a(2), ctx.a(3), ctx.a(4), ctx.a(5)];
-                    let syscall_ret = tg_syscall::handle(Caller { entity: 0, flow: 0 }, id, args);
-
-                    // ─── 信号处理 ───
-                    let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
-                    match current_proc.signal.handle_signals(ctx) {
-                        SignalResult::ProcessKilled(exit_code) => unsafe {
-                            (*processor).make_current_exited(exit_code as _)
-                        },
-                        _ => match syscall_ret {
-                            Ret::Done(ret) => match id {
-                                Id::EXIT => unsafe { (*processor).make_current_exited(ret) },
-                                // ─── 本章新增：同步原语阻塞处理 ───
-                                // 当 semaphore_down / mutex_lock / condvar_wait 返回 -1 时，
-                                // 表示资源不可用，将当前线程标记为阻塞态
-                                Id::SEMAPHORE_DOWN | Id::MUTEX_LOCK | Id::CONDVAR_WAIT => {
-                                    let ctx = &mut task.context.context;
-                                    *ctx.a_mut(0) = ret as _;
-                                    if ret == -1 {
-                                        // 阻塞：从就绪队列移除，等待资源释放后唤醒
-                                        unsafe { (*processor).make_current_blocked() };
-                                    } else {
-                                        // 成功获取：正常挂起（时间片轮转）
-                                        unsafe { (*processor).make_current_suspend() };
-                                    }
+        // 记录本核正在运行的线程，释放大内核锁后进入用户态
+        let tid = task.tid;
+        let pid = unsafe { (*processor).get_current_proc().unwrap().pid };
+        let hart = HARTS.get_mut(cpu);
+        (hart.thread, hart.current) = (Some(tid), Some(tid));
+        let context: *mut ForeignContext = &mut task.context;
+        // 设置本次时间片的截止时刻（若有睡眠线程更早到期，则提前产生时钟中断）
+        let slice_end = timer::now() + TIME_SLICE;
+        timer::program(slice_end);
+        drop(guard);
+
+        let enter_time = timer::now();
+        unsafe { (*context).execute(portal, cpu) };
+        let trap_time = timer::now();
+
+        let _guard = smp::lock(cpu);
+        HARTS.get_mut(cpu).current = None;
+        let task = PROCESSOR.restore_current().unwrap();
+        // 默认视为主动让出，抢占与阻塞分支会覆盖此值
+        task.last_switch = SwitchReason::Voluntary;
+        // 先记入用户态时间，使本次系统调用读到的 CPU 时间包含刚结束的这段运行
+        charge(tid, pid, |usage| {
+            usage.utime += trap_time - enter_time;
+            usage.nswitch += 1;
+        });
+
+        let cause = scause::read().cause();
+        // 被中断打断的线程是被动让出，阻塞或 sched_yield 是主动让出
+        let involuntary = matches!(cause, scause::Trap::Interrupt(_));
+        let mut voluntary = false;
+        match cause {
+            // ─── 同进程的 exec 已结束本线程：不再处理本次 Trap，直接退出 ───
+            _ if task.killed => processor::exit_current(0),
+            // ─── 时钟中断：时间片用完或有睡眠线程到期，当前线程重新排队 ───
+            scause::Trap::Interrupt(scause::Interrupt::SupervisorTimer) => {
+                tg_sbi::set_timer(u64::MAX);
+                if timer::now() >= slice_end {
+                    task.last_switch = SwitchReason::Preempted;
+                }
+                if deliver_signals(&mut task.context.context) {
+                    unsafe { (*processor).make_current_suspend() };
+                }
+            }
+            // ─── 软件中断：其他核发来的 IPI，线程照常重新排队 ───
+            scause::Trap::Interrupt(scause::Interrupt::SupervisorSoft) => {
+                smp::clear_ipi();
+                if deliver_signals(&mut task.context.context) {
+                    unsafe { (*processor).make_current_suspend() };
+                }
+            }
+            // ─── 系统调用 ───
+            scause::Trap::Exception(scause::Exception::UserEnvCall) => {
+                use tg_syscall::{SyscallId as Id, SyscallResult as Ret};
+                let ctx = &mut task.context.context;
+                ctx.move_next();
+                let id: Id = ctx.a(7).into();
+                let args = [ctx.a(0), ctx.a(1), ctx.a(2), ctx.a(3), ctx.a(4), ctx.a(5)];
+                voluntary = id == Id::SCHED_YIELD;
+                let syscall_ret = match syscall_ext::handle(id, args) {
+                    Ret::Unsupported(id) => tg_syscall::handle(Caller { entity: 0, flow: 0 }, id, args),
+                    ret => ret,
+                };
+                // 需要重新执行的系统调用先回退 pc，使信号处理函数返回后同样回到 ecall
+                let restart = syscall_ext::take_restart();
+                if restart.is_some() {
+                    *ctx.pc_mut() -= 4;
+                }
+
+                // ─── 信号处理 ───
+                let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
+                let signal = current_proc.signal.handle_signals(ctx);
+                // 收到停止信号：本该重新排队的线程改为挂起（阻塞中的线程下次陷入内核时再停止）
+                let stopped = matches!(signal, SignalResult::ProcessSuspended);
+                let suspend = || match stopped {
+                    true => processor::stop_current(),
+                    false => unsafe { (*processor).make_current_suspend() },
+                };
+                match signal {
+                    SignalResult::ProcessKilled(exit_code) => {
+                        processor::kill_current(exit_code as _)
+                    }
+                    _ => match syscall_ret {
+                        // 等待条件不满足：阻塞到被唤醒或让出 CPU，之后重新执行；
+                        // 刚进入信号处理函数时不阻塞，让处理函数先运行
+                        Ret::Done(_) if restart.is_some() => {
+                            voluntary = true;
+                            match restart {
+                                Some(syscall_ext::Restart::Block)
+                                    if !stopped && !matches!(signal, SignalResult::Handled) =>
+                                {
+                                    task.last_switch = SwitchReason::Blocked;
+                                    task.interruptible = true;
+                                    unsafe { (*processor).make_current_blocked() };
                                 }
-                                _ => {
-                                    let ctx = &mut task.context.context;
-                                    *ctx.a_mut(0) = ret as _;
-                                    unsafe { (*processor).make_current_suspend() };
+                                _ => suspend(),
+                            }
+                        }
+                        Ret::Done(ret) => match id {
+                            Id::EXIT => processor::exit_current(ret),
+                            // ─── 本章新增：同步原语阻塞处理 ───
+                            // 当 semaphore_down / mutex_lock / condvar_wait 返回 -1 时，
+                            // 表示资源不可用，将当前线程标记为阻塞态
+                            Id::SEMAPHORE_DOWN | Id::MUTEX_LOCK | Id::CONDVAR_WAIT => {
+                                let ctx = &mut task.context.context;
+                                *ctx.a_mut(0) = ret as _;
+                                if ret == -1 {
+                                    // 阻塞：从就绪队列移除，等待资源释放后唤醒；
+                                    // 唤醒即意味着获得资源，只有终止进程的信号才能打断
+                                    voluntary = true;
+                                    task.last_switch = SwitchReason::Blocked;
+                                    task.interruptible = false;
+                                    unsafe { (*processor).make_current_blocked() };
+                                } else {
+                                    // 成功获取：正常挂起（时间片轮转）
+                                    suspend();
                                 }
-                            },
-                            Ret::Unsupported(_) => {
-                                log::info!("id = {id:?}");
-                                unsafe { (*processor).make_current_exited(-2) };
+                            }
+                            _ => {
+                                let ctx = &mut task.context.context;
+                                *ctx.a_mut(0) = ret as _;
+                                suspend();
                             }
                         },
-                    }
+                        Ret::Unsupported(_) => {
+                            log::info!("id = {id:?}");
+                            processor::exit_current(-2);
+                        }
+                    },
                 }
-                e => {
-                    log::error!("unsupported trap: {e:?}");
-                    log::error!("stval = {:#x}", stval::read());
-                    log::error!("sepc  = {:#x}", sepc::read());
-                    unsafe { (*processor).make_current_exited(-3) };
+            }
+            // ─── 硬件异常：内核能够处理的缺页异常（写时复制、文件映射调入页）直接处理，其余转换为信号交给进程处理 ───
+            scause::Trap::Exception(e) if fault_signal(e).is_some() => {
+                let signal = fault_signal(e).unwrap();
+                let (pc, addr) = (sepc::read(), stval::read());
+                let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
+                let access = page_fault_access(e);
+                if access.is_some_and(|access| current_proc.handle_page_fault(addr, access)) {
+                    // 页已就绪，重新执行出错的指令
+                    unsafe { (*processor).make_current_suspend() };
+                } else {
+                    log::info!("{e:?} at {pc:#x}, stval = {addr:#x}: signal {}", signal as usize);
+                    current_proc.signal.add_signal(signal);
+                    match current_proc.signal.handle_signals(&mut task.context.context) {
+                        // 进入处理函数；处理函数返回后重新执行出错的指令
+                        SignalResult::Handled => unsafe { (*processor).make_current_suspend() },
+                        SignalResult::ProcessKilled(exit_code) => {
+                            processor::kill_current(exit_code as _)
+                        }
+                        SignalResult::ProcessSuspended => processor::stop_current(),
+                        // 信号被屏蔽、忽略或正在处理其他信号：返回用户态只会再次出错，按默认动作终止进程
+                        _ => processor::kill_current(-(signal as isize)),
+                    }
                 }
             }
-        } else {
-            println!("no task");
-            break;
+            e => {
+                log::error!("unsupported trap: {e:?}");
+                log::error!("stval = {:#x}", stval::read());
+                log::error!("sepc  = {:#x}", sepc::read());
+                processor::exit_current(-3);
+            }
         }
+        // 记入本次 Trap 的处理时间（线程或进程可能已在处理中退出）
+        let stime = timer::now() - trap_time;
+        charge(tid, pid, |usage| {
+            usage.stime += stime;
+            usage.nvcsw += voluntary as u64;
+            usage.nivcsw += involuntary as u64;
+        });
+        rlimit::check_cpu(pid);
+        // 线程已退出：回收分离线程，唤醒在 wait4、waittid 中阻塞的线程重新检查
+        if unsafe { (*processor).get_task(tid).is_none() } {
+            processor::reap_detached(tid, pid);
+            processor::wake_exit_waiters(unsafe { &mut *processor });
+        }
+        HARTS.get_mut(cpu).thread = None;
+        // 本次 Trap 可能让线程进入就绪队列，唤醒空闲核来分担
+        HARTS.kick_idle();
     }
 
     tg_sbi::shutdown(false)
 }
 
+/// 硬件异常对应的信号，不由信号处理的异常返回 `None`
+fn fault_signal(e: scause::Exception) -> Option<SignalNo> {
+    use scause::Exception::*;
+    match e {
+        InstructionPageFault | LoadPageFault | StorePageFault | InstructionFault | LoadFault
+        | StoreFault => Some(SignalNo::SIGSEGV),
+        IllegalInstruction => Some(SignalNo::SIGILL),
+        InstructionMisaligned | LoadMisaligned | StoreMisaligned => Some(SignalNo::SIGBUS),
+        Breakpoint => Some(SignalNo::SIGTRAP),
+        _ => None,
+    }
+}
+
+/// 缺页异常对应的访问类型，其他异常返回 `None`
+fn page_fault_access(e: scause::Exception) -> Option<mmap::Access> {
+    use scause::Exception::*;
+    match e {
+        LoadPageFault => Some(mmap::Access::Read),
+        StorePageFault => Some(mmap::Access::Write),
+        InstructionPageFault => Some(mmap::Access::Execute),
+        _ => None,
+    }
+}
+
+/// 在中断返回用户态前处理当前进程的信号
+///
+/// 使正在计算、不发起系统调用的进程也能被 Ctrl-C 终止或被 Ctrl-Z 停止。
+/// 进程被终止或停止时返回 `false`，此时当前线程已离开就绪队列。
+fn deliver_signals(ctx: &mut tg_kernel_context::LocalContext) -> bool {
+    let current_proc = PROCESSOR.get_mut().get_current_proc().unwrap();
+    match current_proc.signal.handle_signals(ctx) {
+        SignalResult::ProcessKilled(exit_code) => {
+            processor::kill_current(exit_code as _);
+            false
+        }
+        SignalResult::ProcessSuspended => {
+            processor::stop_current();
+            false
+        }
+        _ => true,
+    }
+}
+
+/// 同时更新线程 `tid` 及其所属进程 `pid` 的 CPU 使用统计（已回收者跳过）
+fn charge(tid: ThreadId, pid: ProcId, f: impl Fn(&mut CpuUsage)) {
+    let processor = PROCESSOR.get_mut();
+    if let Some(thread) = processor.get_task(tid) {
+        f(&mut thread.usage);
+    }
+    if let Some(process) = processor.get_proc(pid) {
+        f(&mut process.usage);
+    }
+}
+
 /// panic 处理
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
@@ -462,25 +756,29 @@ fn map_portal(space: &AddressSpace<Sv39, Sv39Manager>) {
 mod impls {
     use crate::{
         build_flags,
-        fs::{read_all, Fd, FS},
-        processor::ProcessorInner,
-        Sv39, Thread, PROCESSOR,
+        fs::{Fd, FS},
+        mmap::Access,
+        proc_tree::PROC_TREE,
+        process::MAX_PRIORITY,
+        processor::{self, ProcessorInner},
+        rlimit::RLIMIT_NPROC,
+        thread_stack::DEFAULT_STACK_PAGES,
+        timer, Sv39, PROCESSOR,
     };
     use alloc::sync::Arc;
-    use alloc::{alloc::alloc_zeroed, string::String, vec::Vec};
+    use alloc::{alloc::alloc_zeroed, collections::BTreeMap, string::String, vec::Vec};
     use core::{alloc::Layout, ptr::NonNull};
     use spin::Mutex;
     use tg_console::log;
     use tg_easy_fs::{make_pipe, FSManager, OpenFlags, UserBuffer};
     use tg_kernel_vm::{
-        page_table::{MmuMeta, Pte, VAddr, VmFlags, VmMeta, PPN, VPN},
+        page_table::{MmuMeta, Pte, VAddr, VmFlags, PPN, VPN},
         PageManager,
     };
     use tg_signal::SignalNo;
     use tg_sync::{Condvar, Mutex as MutexTrait, MutexBlocking, Semaphore};
     use tg_syscall::*;
     use tg_task_manage::{ProcId, ThreadId};
-    use xmas_elf::ElfFile;
 
     // ─── Sv39 页表管理器 ───
 
@@ -488,6 +786,9 @@ mod impls {
     #[repr(transparent)]
     pub struct Sv39Manager(NonNull<Pte<Sv39>>);
 
+    /// 被多个地址空间共享的物理页（写时复制的 fork）：页号 → 共享的地址空间个数，只记录 2 个及以上的页
+    static SHARED_FRAMES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
     impl Sv39Manager {
         const OWNED: VmFlags<Sv39> = unsafe { VmFlags::from_raw(1 << 8) };
         #[inline]
@@ -500,6 +801,35 @@ mod impls {
             }
             .cast()
         }
+
+        /// 物理页 `ppn` 多了一个共享它的地址空间
+        pub fn share(ppn: PPN<Sv39>) {
+            *SHARED_FRAMES.lock().entry(ppn.val()).or_insert(1) += 1;
+        }
+
+        /// 物理页 `ppn` 是否被多个地址空间共享
+        pub fn is_shared(ppn: PPN<Sv39>) -> bool {
+            SHARED_FRAMES.lock().contains_key(&ppn.val())
+        }
+
+        /// 一个地址空间不再使用从 `ppn` 开始的 `len` 页，没有其他地址空间共享时释放
+        pub fn release(ppn: PPN<Sv39>, len: usize) {
+            let mut shared = SHARED_FRAMES.lock();
+            if let Some(count) = shared.get_mut(&ppn.val()) {
+                *count -= 1;
+                if *count == 1 {
+                    shared.remove(&ppn.val());
+                }
+                return;
+            }
+            let ptr = VPN::<Sv39>::new(ppn.val()).base().as_mut_ptr::<u8>();
+            unsafe {
+                alloc::alloc::dealloc(
+                    ptr,
+                    Layout::from_size_align_unchecked(len << Sv39::PAGE_BITS, 1 << Sv39::PAGE_BITS),
+                );
+            }
+        }
     }
 
     impl PageManager<Sv39> for Sv39Manager {
@@ -530,14 +860,7 @@ mod impls {
         }
         fn deallocate(&mut self, pte: Pte<Sv39>, len: usize) -> usize {
             if self.check_owned(pte) {
-                let ppn = pte.ppn();
-                let ptr = self.p_to_v::<u8>(ppn).as_ptr();
-                unsafe {
-                    alloc::alloc::dealloc(
-                        ptr,
-                        Layout::from_size_align_unchecked(len << Sv39::PAGE_BITS, 1 << Sv39::PAGE_BITS),
-                    );
-                }
+                Self::release(pte.ppn(), len);
             }
             0
         }
@@ -565,7 +888,7 @@ mod impls {
     /// 系统调用上下文
     pub struct SyscallContext;
     const READABLE: VmFlags<Sv39> = build_flags("RV");
-    const WRITEABLE: VmFlags<Sv39> = build_flags("W_V");
+    const WRITABLE: VmFlags<Sv39> = build_flags("W_V");
 
     /// IO 系统调用（与第七章基本相同）
     ///
@@ -574,23 +897,27 @@ mod impls {
     impl IO for SyscallContext {
         fn write(&self, _caller: Caller, fd: usize, buf: usize, count: usize) -> isize {
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 先调入缓冲区的页（文件映射、栈），下面借用 fd 表期间只能直接翻译
+            current.fault_in(buf, count, Access::Read);
             
-            if fd == STDOUT || fd == STDDEBUG {
-                if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf), READABLE) {
-                    print!("{}", unsafe {
-                        core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                            ptr.as_ptr(), count,
-                        ))
-                    });
-                    return count as _;
-                } else {
-                    log::error!("sys_write: buffer at {:#x} not readable", buf);
-                    return -1;
-                }
-            } else if let Some(file) = &current.fd_table[fd] {
+            // 按 fd 表中的描述符分派：fd 0..=2 也可能被 dup 或 spawn 换成管道、文件
+            if let Some(Some(file)) = current.fd_table.get(fd) {
                 let file_guard = file.lock();
                 if file_guard.writable() {
                     match &*file_guard {
+                        Fd::Console { .. } => {
+                            if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf), READABLE) {
+                                print!("{}", unsafe {
+                                    core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                                        ptr.as_ptr(), count,
+                                    ))
+                                });
+                                return count as _;
+                            } else {
+                                log::error!("sys_write: buffer at {:#x} not readable", buf);
+                                return -1;
+                            }
+                        }
                         Fd::VirtioGpu => {
                             // ── Framebuffer write Page-By-Page ──
                             unsafe {
@@ -660,25 +987,21 @@ mod impls {
 
         fn read(&self, _caller: Caller, fd: usize, buf: usize, count: usize) -> isize {
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 先调入缓冲区的页并解除写时复制，下面借用 fd 表期间只能直接翻译
+            current.fault_in(buf, count, Access::Write);
             
-            if fd == STDIN {
-                if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf), WRITEABLE) {
-                    let mut ptr = ptr.as_ptr();
-                    for _ in 0..count {
-                        unsafe { *ptr = tg_sbi::console_getchar() as u8; ptr = ptr.add(1); }
-                    }
-                    return count as _;
-                } else {
-                    log::error!("sys_read: buffer at {:#x} not writeable", buf);
-                    return -1;
-                }
-            } else if let Some(file) = &current.fd_table[fd] {
+            if let Some(Some(file)) = current.fd_table.get(fd) {
                 let file_guard = file.lock();
                 if file_guard.readable() {
                     match &*file_guard {
+                        Fd::Console { .. } => {
+                            // 读控制台由 `syscall_ext` 接管（经控制台缓冲读取，可阻塞），不会走到这里
+                            log::error!("sys_read: console should be handled by syscall_ext");
+                            return -1;
+                        }
                         Fd::VirtioInput => {
                             // ── VirtIO-Input KEY_STATES read ──
-                            if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf), WRITEABLE) {
+                            if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf), WRITABLE) {
                                 unsafe {
                                     let dst = core::slice::from_raw_parts_mut(ptr.as_ptr(), count.min(256));
                                     for i in 0..count.min(256) {
@@ -696,7 +1019,7 @@ mod impls {
                             let mut count_left = count;
                             let mut buf_addr = buf;
                             while count_left > 0 {
-                                if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf_addr), WRITEABLE) {
+                                if let Some(ptr) = current.address_space.translate::<u8>(VAddr::new(buf_addr), WRITABLE) {
                                     let page_offset = buf_addr % 4096;
                                     let copy_size = count_left.min(4096 - page_offset);
                                     unsafe {
@@ -725,11 +1048,11 @@ mod impls {
 
         fn open(&self, _caller: Caller, path: usize, flags: usize) -> isize {
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            if let Some(_ptr) = current.address_space.translate::<u8>(VAddr::new(path), READABLE) {
+            if let Some(_ptr) = current.translate::<u8>(path) {
                 let mut string = String::new();
                 let mut vaddr = path;
                 loop {
-                    if let Some(ptr) = current.address_space.translate(VAddr::new(vaddr), READABLE) {
+                    if let Some(ptr) = current.translate(vaddr) {
                         unsafe {
                             let ch: u8 = *ptr.as_ptr();
                             if ch == 0 { break; }
@@ -741,6 +1064,10 @@ mod impls {
                         return -1;
                     }
                 }
+                if !current.can_open(1) {
+                    log::error!("sys_open: RLIMIT_NOFILE exceeded");
+                    return -1;
+                }
                 if string == "/dev/gpu" {
                     let new_fd = current.fd_table.len();
                     current.fd_table.push(Some(Mutex::new(Fd::VirtioGpu)));
@@ -774,14 +1101,13 @@ mod impls {
         /// pipe 系统调用
         fn pipe(&self, _caller: Caller, pipe: usize) -> isize {
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            if !current.can_open(2) { return -1; }
             let (read_end, write_end) = make_pipe();
             let read_fd = current.fd_table.len();
             let write_fd = read_fd + 1;
-            if let Some(mut ptr) = current.address_space
-                .translate::<usize>(VAddr::new(pipe), WRITEABLE)
+            if let Some(mut ptr) = current.translate_mut::<usize>(pipe)
             { unsafe { *ptr.as_mut() = read_fd }; } else { return -1; }
-            if let Some(mut ptr) = current.address_space
-                .translate::<usize>(VAddr::new(pipe + core::mem::size_of::<usize>()), WRITEABLE)
+            if let Some(mut ptr) = current.translate_mut::<usize>(pipe + core::mem::size_of::<usize>())
             { unsafe { *ptr.as_mut() = write_fd }; } else { return -1; }
             current.fd_table.push(Some(Mutex::new(Fd::PipeRead(read_end))));
             current.fd_table.push(Some(Mutex::new(Fd::PipeWrite(write_end))));
@@ -799,9 +1125,13 @@ mod impls {
             let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
             let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
             let parent_pid = current_proc.pid;
+            if !current_proc.rlimits.allows(RLIMIT_NPROC, PROC_TREE.lock().session_size(parent_pid), 1) {
+                return -1;
+            }
             let (proc, mut thread) = current_proc.fork().unwrap();
             let pid = proc.pid;
             *thread.context.context.a_mut(0) = 0 as _;
+            PROC_TREE.lock().insert(pid, parent_pid);
             unsafe {
                 (*processor).add_proc(pid, proc, parent_pid);
                 (*processor).add(thread.tid, thread, pid);
@@ -809,40 +1139,15 @@ mod impls {
             pid.get_usize() as isize
         }
 
-        /// exec：从文件系统加载新程序
-        fn exec(&self, _caller: Caller, path: usize, count: usize) -> isize {
-            const READABLE: VmFlags<Sv39> = build_flags("RV");
-            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            current.address_space
-                .translate(VAddr::new(path), READABLE)
-                .map(|ptr| unsafe {
-                    core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr.as_ptr(), count))
-                })
-                .and_then(|name| FS.open(name, OpenFlags::RDONLY))
-                .map_or_else(
-                    || {
-                        log::error!("unknown app, select one in the list: ");
-                        FS.readdir("").unwrap().into_iter().for_each(|app| println!("{app}"));
-                        println!();
-                        -1
-                    },
-                    |fd| { current.exec(ElfFile::new(&read_all(fd)).unwrap()); 0 },
-                )
-        }
+        /// exec：只带路径的接口
+        ///
+        /// `EXEC` 调用号已由 `syscall_ext` 按 execve(path, argv, envp) 接管，这里不会被调用。
+        fn exec(&self, _caller: Caller, _path: usize, _count: usize) -> isize { -1 }
 
-        fn wait(&self, _caller: Caller, pid: isize, exit_code_ptr: usize) -> isize {
-            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-            let current = unsafe { (*processor).get_current_proc().unwrap() };
-            const WRITABLE: VmFlags<Sv39> = build_flags("W_V");
-            if let Some((dead_pid, exit_code)) =
-                unsafe { (*processor).wait(ProcId::from_usize(pid as usize)) }
-            {
-                if let Some(mut ptr) = current.address_space
-                    .translate::<i32>(VAddr::new(exit_code_ptr), WRITABLE)
-                { unsafe { *ptr.as_mut() = exit_code as i32 }; }
-                return dead_pid.get_usize() as isize;
-            } else { return -1; }
-        }
+        /// wait：等待子进程
+        ///
+        /// `WAIT` 调用号已由 `syscall_ext` 按 wait4(pid, status, options) 接管，这里不会被调用。
+        fn wait(&self, _caller: Caller, _pid: isize, _exit_code_ptr: usize) -> isize { -1 }
 
         fn getpid(&self, _caller: Caller) -> isize {
             PROCESSOR.get_mut().get_current_proc().unwrap().pid.get_usize() as _
@@ -852,44 +1157,69 @@ mod impls {
     impl Scheduling for SyscallContext {
         #[inline]
         fn sched_yield(&self, _caller: Caller) -> isize { 0 }
+
+        /// set_priority：设置**当前线程**的调度优先级
+        ///
+        /// 优先级必须 ≥ 2，成功返回设置的值，否则返回 -1。
+        /// 超过 `MAX_PRIORITY` 的优先级按 `MAX_PRIORITY` 计，保证 stride 步长不为 0。
+        fn set_priority(&self, _caller: Caller, prio: isize) -> isize {
+            if prio < 2 { return -1; }
+            PROCESSOR.get_mut().current().unwrap().priority = (prio as usize).min(MAX_PRIORITY);
+            prio
+        }
     }
 
     impl Clock for SyscallContext {
+        /// clock_gettime：支持单调时钟，以及当前进程 / 线程的 CPU 时间
         #[inline]
         fn clock_gettime(&self, _caller: Caller, clock_id: ClockId, tp: usize) -> isize {
-            const WRITABLE: VmFlags<Sv39> = build_flags("W_V");
-            match clock_id {
-                ClockId::CLOCK_MONOTONIC => {
-                    if let Some(mut ptr) = PROCESSOR.get_mut().get_current_proc().unwrap()
-                        .address_space.translate(VAddr::new(tp), WRITABLE)
-                    {
-                        let time = riscv::register::time::read() * 10000 / 125;
-                        *unsafe { ptr.as_mut() } = TimeSpec {
-                            tv_sec: time / 1_000_000_000,
-                            tv_nsec: time % 1_000_000_000,
-                        };
-                        0
-                    } else { log::error!("ptr not readable"); -1 }
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let time = match clock_id {
+                ClockId::CLOCK_MONOTONIC => timer::ticks_to_timespec(timer::now()),
+                ClockId::CLOCK_PROCESS_CPUTIME_ID => {
+                    let usage = unsafe { (*processor).get_current_proc().unwrap().usage };
+                    timer::ticks_to_timespec(usage.utime + usage.stime)
                 }
-                _ => -1,
-            }
+                ClockId::CLOCK_THREAD_CPUTIME_ID => {
+                    let usage = unsafe { (*processor).current().unwrap().usage };
+                    timer::ticks_to_timespec(usage.utime + usage.stime)
+                }
+                _ => return -1,
+            };
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            if let Some(mut ptr) = current.translate_mut(tp) {
+                *unsafe { ptr.as_mut() } = time;
+                0
+            } else { log::error!("ptr not readable"); -1 }
         }
     }
 
     /// 信号系统调用（与第七章相同）
     impl Signal for SyscallContext {
+        /// `pid` 大于 0 为单个进程，0 为调用者所在进程组，小于 -1 为进程组 `-pid`，
+        /// -1 为除 initproc 和调用者以外的所有进程。没有进程收到信号时返回 -1。
         fn kill(&self, _caller: Caller, pid: isize, signum: u8) -> isize {
-            if let Some(target_task) = PROCESSOR.get_mut()
-                .get_proc(ProcId::from_usize(pid as usize))
-            {
-                if let Ok(signal_no) = SignalNo::try_from(signum) {
-                    if signal_no != SignalNo::ERR {
-                        target_task.signal.add_signal(signal_no);
-                        return 0;
-                    }
+            let Ok(signal_no) = SignalNo::try_from(signum) else { return -1 };
+            if signal_no == SignalNo::ERR { return -1; }
+            let caller = PROCESSOR.get_mut().get_current_proc().unwrap().pid;
+            let targets = {
+                let tree = PROC_TREE.lock();
+                match pid {
+                    pid if pid > 0 => alloc::vec![ProcId::from_usize(pid as usize)],
+                    0 => tree.pgid(caller).map_or(Vec::new(), |pgid| tree.group(pgid)),
+                    -1 => tree
+                        .alive()
+                        .into_iter()
+                        .filter(|&pid| pid != caller && Some(&pid) != INITPROC.get())
+                        .collect(),
+                    pid => tree.group(ProcId::from_usize(-pid as usize)),
                 }
+            };
+            let mut delivered = false;
+            for target in targets {
+                delivered |= processor::send_signal(target, signal_no);
             }
-            -1
+            if delivered { 0 } else { -1 }
         }
 
         fn sigaction(&self, _caller: Caller, signum: u8, action: usize, old_action: usize) -> isize {
@@ -898,14 +1228,14 @@ mod impls {
             if let Ok(signal_no) = SignalNo::try_from(signum) {
                 if signal_no == SignalNo::ERR { return -1; }
                 if old_action as usize != 0 {
-                    if let Some(mut ptr) = current.address_space.translate(VAddr::new(old_action), WRITEABLE) {
+                    if let Some(mut ptr) = current.translate_mut(old_action) {
                         if let Some(signal_action) = current.signal.get_action_ref(signal_no) {
                             *unsafe { ptr.as_mut() } = signal_action;
                         } else { return -1; }
                     } else { return -1; }
                 }
                 if action as usize != 0 {
-                    if let Some(ptr) = current.address_space.translate(VAddr::new(action), READABLE) {
+                    if let Some(ptr) = current.translate(action) {
                         if !current.signal.set_action(signal_no, &unsafe { *ptr.as_ptr() }) { return -1; }
                     } else { return -1; }
                 }
@@ -928,36 +1258,11 @@ mod impls {
 
     /// 线程系统调用（**本章新增**）
     impl tg_syscall::Thread for SyscallContext {
-        /// thread_create：在当前进程中创建新线程
+        /// thread_create：在当前进程中创建新线程，使用默认大小的栈
         ///
-        /// 为新线程分配独立的用户栈（从高地址向下搜索未映射的页面），
-        /// 创建新的执行上下文，入口为 entry，参数为 arg。
+        /// 实现见 `syscall_ext::thread_create`。
         fn thread_create(&self, _caller: Caller, entry: usize, arg: usize) -> isize {
-            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-            let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
-            // 从最高用户栈位置向下搜索空闲的页表区域
-            let mut vpn = VPN::<Sv39>::new((1 << 26) - 2);
-            let addrspace = &mut current_proc.address_space;
-            loop {
-                let idx = vpn.index_in(Sv39::MAX_LEVEL);
-                if !addrspace.root()[idx].is_valid() { break; }
-                vpn = VPN::<Sv39>::new(vpn.val() - 3);
-            }
-            // 分配 2 页用户栈
-            let stack = unsafe {
-                alloc_zeroed(Layout::from_size_align_unchecked(
-                    2 << Sv39::PAGE_BITS, 1 << Sv39::PAGE_BITS,
-                ))
-            };
-            addrspace.map_extern(vpn..vpn + 2, PPN::new(stack as usize >> Sv39::PAGE_BITS), build_flags("U_WRV"));
-            let satp = (8 << 60) | addrspace.root_ppn().val();
-            let mut context = tg_kernel_context::LocalContext::user(entry);
-            *context.sp_mut() = (vpn + 2).base().val();
-            *context.a_mut(0) = arg;
-            let thread = Thread::new(satp, context);
-            let tid = thread.tid;
-            unsafe { (*processor).add(tid, thread, current_proc.pid); }
-            tid.get_usize() as _
+            crate::syscall_ext::thread_create(entry, arg, DEFAULT_STACK_PAGES << Sv39::PAGE_BITS)
         }
 
         /// gettid：获取当前线程 TID
@@ -966,14 +1271,9 @@ mod impls {
         }
 
         /// waittid：等待指定线程退出
-        fn waittid(&self, _caller: Caller, tid: usize) -> isize {
-            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-            let current_thread = unsafe { (*processor).current().unwrap() };
-            if tid == current_thread.tid.get_usize() { return -1; }
-            if let Some(exit_code) = unsafe { (*processor).waittid(ThreadId::from_usize(tid)) } {
-                exit_code
-            } else { -1 }
-        }
+        ///
+        /// `WAITTID` 调用号已由 `syscall_ext` 接管（可阻塞），这里不会被调用。
+        fn waittid(&self, _caller: Caller, _tid: usize) -> isize { -1 }
     }
 
     /// 同步原语系统调用（**本章新增**）
@@ -1002,7 +1302,7 @@ mod impls {
             let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
             let sem = Arc::clone(current_proc.semaphore_list[sem_id].as_ref().unwrap());
             if let Some(tid) = sem.up() {
-                unsafe { (*processor).re_enque(tid); }
+                processor::wake(unsafe { &mut *processor }, tid);
             }
             0
         }
@@ -1040,7 +1340,7 @@ mod impls {
             let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
             let mutex = Arc::clone(current_proc.mutex_list[mutex_id].as_ref().unwrap());
             if let Some(tid) = mutex.unlock() {
-                unsafe { (*processor).re_enque(tid); }
+                processor::wake(unsafe { &mut *processor }, tid);
             }
             0
         }
@@ -1076,7 +1376,7 @@ mod impls {
             let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
             let condvar = Arc::clone(current_proc.condvar_list[condvar_id].as_ref().unwrap());
             if let Some(tid) = condvar.signal() {
-                unsafe { (*processor).re_enque(tid); }
+                processor::wake(unsafe { &mut *processor }, tid);
             }
             0
         }
@@ -1098,7 +1398,7 @@ mod impls {
             let flag = mutex.lock(tid);
             
             if let Some(waking_tid) = waking_tid {
-                unsafe { (*processor).re_enque(waking_tid); }
+                processor::wake(unsafe { &mut *processor }, waking_tid);
             }
             if !flag { -1 } else { 0 }
         }
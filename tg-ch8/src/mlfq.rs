@@ -0,0 +1,119 @@
+//! 多级反馈队列（MLFQ）调度器
+//!
+//! 作为默认 stride 调度之外的另一种调度策略（`processor::ThreadManager` 的 `Policy`），通过 `mlfq` feature 启用，
+//! 用于与 stride 调度和 FIFO 调度（`fifo` feature）比较：
+//!
+//! ```bash
+//! cargo run --features mlfq
+//! ```
+//!
+//! ## 规则
+//!
+//! - 新线程进入最高层（level 0）；
+//! - 总是从最高的非空层取线程，同层内轮转（FIFO）；
+//! - 线程用完整个时间片被抢占（`SwitchReason::Preempted`），降低一层；
+//! - 线程因同步原语或 I/O 阻塞后被唤醒（`SwitchReason::Blocked`），提升一层；
+//! - 每隔 `BOOST_PERIOD` 把所有线程重新提升到最高层，避免饥饿。
+//!
+//! 这样 `user_shell`、`doom` 这类交互式程序不会排在计算密集型线程后面。
+
+use crate::process::{SwitchReason, Thread};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+};
+use riscv::register::time;
+use tg_task_manage::{Manage, Schedule, ThreadId};
+
+/// 队列层数
+const LEVELS: usize = 4;
+/// 全局提升周期（时钟周期数），默认为 64 个时间片
+const BOOST_PERIOD: u64 = crate::TIME_SLICE * 64;
+
+/// 多级反馈队列调度策略
+///
+/// 维护所有线程实体和 `LEVELS` 个就绪队列。
+pub struct Mlfq {
+    /// 线程实体表（TID → Thread）
+    tasks: BTreeMap<ThreadId, Box<Thread>>,
+    /// 各层就绪队列，下标越小优先级越高
+    queues: [VecDeque<ThreadId>; LEVELS],
+    /// 上一次全局提升的时刻
+    last_boost: u64,
+}
+
+impl Mlfq {
+    /// 创建空的调度策略
+    pub fn new() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            queues: Default::default(),
+            last_boost: time::read() as u64,
+        }
+    }
+
+    /// 将所有线程提升到最高层
+    fn boost(&mut self) {
+        for thread in self.tasks.values_mut() {
+            thread.level = 0;
+        }
+        let (top, rest) = self.queues.split_at_mut(1);
+        for queue in rest {
+            top[0].append(queue);
+        }
+    }
+}
+
+impl Manage<Thread, ThreadId> for Mlfq {
+    /// 插入线程实体（新线程位于最高层）
+    #[inline]
+    fn insert(&mut self, id: ThreadId, mut task: Thread) {
+        task.level = 0;
+        self.tasks.insert(id, Box::new(task));
+    }
+    /// 获取线程可变引用
+    #[inline]
+    fn get_mut(&mut self, id: ThreadId) -> Option<&mut Thread> {
+        self.tasks.get_mut(&id).map(|t| &mut **t)
+    }
+    /// 删除线程实体
+    #[inline]
+    fn delete(&mut self, id: ThreadId) { self.tasks.remove(&id); }
+}
+
+impl Schedule<ThreadId> for Mlfq {
+    /// 按线程上次离开 CPU 的原因调整层级后入队
+    fn add(&mut self, id: ThreadId) {
+        let level = match self.tasks.get_mut(&id) {
+            Some(thread) => {
+                match thread.last_switch {
+                    SwitchReason::Preempted => thread.level = (thread.level + 1).min(LEVELS - 1),
+                    SwitchReason::Blocked => thread.level = thread.level.saturating_sub(1),
+                    SwitchReason::Voluntary => {}
+                }
+                thread.level
+            }
+            None => 0,
+        };
+        self.queues[level].push_back(id);
+    }
+
+    /// 从最高的非空层取出下一个线程，必要时先做全局提升
+    fn fetch(&mut self) -> Option<ThreadId> {
+        let now = time::read() as u64;
+        if now.wrapping_sub(self.last_boost) >= BOOST_PERIOD {
+            self.last_boost = now;
+            self.boost();
+        }
+        // 跳过已退出线程留下的 TID
+        let tasks = &self.tasks;
+        self.queues.iter_mut().find_map(|queue| {
+            while let Some(id) = queue.pop_front() {
+                if tasks.contains_key(&id) {
+                    return Some(id);
+                }
+            }
+            None
+        })
+    }
+}
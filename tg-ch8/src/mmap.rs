@@ -0,0 +1,449 @@
+//! 内存映射：mmap / munmap / mprotect / msync
+//!
+//! 每个进程在 `Process::mmap` 中记录自己用 mmap 建立的区域及其权限
+//! （`AddressSpace` 只记录映射了哪些页，不记录区域边界和权限）。
+//! 区域位于 `[MMAP_BASE, MMAP_END)`，未指定地址时从低到高选第一个足够大的空隙。
+//!
+//! - 匿名映射：映射时立即分配清零的物理页；
+//! - 文件映射：映射时只登记区域，页在第一次访问产生缺页异常时从 easy-fs 文件读入（见 [`MemoryMap::fault`]）。
+//!   `MAP_PRIVATE` 的页是读入时的副本，修改不影响文件；
+//!   `MAP_SHARED` 的页先以只读映射，第一次写入时记为脏页，msync、munmap 或进程结束时经 `write_at` 写回文件。
+//!
+//! fork 前父进程先调入全部共享映射的页（见 [`MemoryMap::populate_shared`]），`cow::share` 让子进程映射同一批物理页，
+//! 父子双方的修改立即互相可见；共享映射的页改权限时原地修改页表项，不复制物理页。
+//! 内核没有页缓存：各自对同一文件调用 mmap 得到的共享映射各有一份副本，只在写回后通过文件看到彼此的修改。
+//! 系统调用的缓冲区落在尚未调入的页上时，内核经 `Process::fault_in` 同样调用 [`MemoryMap::fault`] 调入。
+//!
+//! munmap、mprotect 可以作用于区域的一部分，区域随之拆分。
+//!
+//! - RISC-V 不允许只写不读的页，`PROT_WRITE` 总是隐含 `PROT_READ`；
+//! - `PROT_NONE` 的页保留物理页但去掉 U 位，用户态访问产生缺页异常；
+//! - `AddressSpace` 不能原地修改页表项的权限，mprotect 逐页复制内容后以新权限重新映射；
+//!   共享映射的页例外，经 `cow::set_flags` 原地修改。
+//!
+//! fork 时区域表随地址空间一起复制，exec 时换成新程序的空表。
+
+use crate::{cow, parse_flags, Sv39, Sv39Manager};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec::Vec,
+};
+use core::ops::Range;
+use tg_easy_fs::Inode;
+use tg_kernel_vm::{
+    page_table::{MmuMeta, VmFlags, VPN},
+    AddressSpace,
+};
+
+/// 页不可访问
+pub const PROT_NONE: usize = 0;
+/// 页可读
+pub const PROT_READ: usize = 1;
+/// 页可写
+pub const PROT_WRITE: usize = 2;
+/// 页可执行
+pub const PROT_EXEC: usize = 4;
+
+/// 共享映射：修改写回文件
+pub const MAP_SHARED: usize = 0x01;
+/// 私有映射
+pub const MAP_PRIVATE: usize = 0x02;
+/// 在 `addr` 处映射，替换区间内已有的映射
+pub const MAP_FIXED: usize = 0x10;
+/// 匿名映射：不对应文件，内容为 0
+pub const MAP_ANONYMOUS: usize = 0x20;
+/// 在 `addr` 处映射，区间已被占用时失败
+pub const MAP_FIXED_NOREPLACE: usize = 0x10_0000;
+
+/// mmap 区域的起始地址
+pub const MMAP_BASE: usize = 1 << 36;
+/// mmap 区域的结束地址（不含），远低于线程栈区域
+pub const MMAP_END: usize = 1 << 37;
+
+const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+
+/// 引起缺页异常的访问类型
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// 读取
+    Read,
+    /// 写入
+    Write,
+    /// 取指
+    Execute,
+}
+
+impl Access {
+    /// 权限 `prot` 是否允许这种访问
+    fn allowed(self, prot: usize) -> bool {
+        match self {
+            Access::Read => prot & (PROT_READ | PROT_WRITE) != 0,
+            Access::Write => prot & PROT_WRITE != 0,
+            Access::Execute => prot & PROT_EXEC != 0,
+        }
+    }
+}
+
+/// 文件映射的来源
+#[derive(Clone)]
+pub struct FileMapping {
+    /// 被映射的文件
+    inode: Arc<Inode>,
+    /// 区域第一页对应的文件偏移
+    offset: usize,
+    /// 映射在文件中的结束偏移：写回不超过这里，避免把整页的填充写进文件
+    end: usize,
+    /// 是否为共享映射
+    shared: bool,
+    /// 文件是否以可写方式打开：否则共享映射不能获得写权限
+    writable: bool,
+    /// 共享映射中被写过、尚未写回的页号
+    dirty: BTreeSet<usize>,
+}
+
+impl FileMapping {
+    /// 从文件偏移 `offset` 开始映射 `len` 字节，`writable` 为文件是否以可写方式打开
+    pub fn new(inode: Arc<Inode>, offset: usize, len: usize, shared: bool, writable: bool) -> Self {
+        Self { inode, offset, end: offset + len, shared, writable, dirty: BTreeSet::new() }
+    }
+}
+
+/// 一段权限相同的映射
+#[derive(Clone)]
+struct Region {
+    /// 结束页号（不含）
+    end: usize,
+    /// 权限（`PROT_*`）
+    prot: usize,
+    /// 映射的文件，匿名映射为 `None`
+    file: Option<FileMapping>,
+}
+
+impl Region {
+    /// 区域中已调入的页 `vpn` 应有的页表权限：共享映射的干净页不可写，以便记录第一次写入
+    fn page_flags(&self, vpn: usize) -> VmFlags<Sv39> {
+        match &self.file {
+            Some(file) if file.shared && !file.dirty.contains(&vpn) => {
+                vm_flags(self.prot & !PROT_WRITE)
+            }
+            _ => vm_flags(self.prot),
+        }
+    }
+}
+
+/// 一个进程的 mmap 区域表：起始页号 → 区域，区域互不重叠
+#[derive(Clone, Default)]
+pub struct MemoryMap {
+    regions: BTreeMap<usize, Region>,
+}
+
+impl MemoryMap {
+    /// 空的区域表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 页号区间 `range` 是否完全位于 mmap 区域内
+    pub fn in_range(range: &Range<usize>) -> bool {
+        range.start >= MMAP_BASE >> Sv39::PAGE_BITS && range.end <= MMAP_END >> Sv39::PAGE_BITS
+    }
+
+    /// 找一段 `pages` 页的空闲区间，返回起始页号；提示的起点 `hint` 可用时优先使用
+    pub fn find_free(&self, hint: usize, pages: usize) -> Option<usize> {
+        let wanted = hint..hint.checked_add(pages)?;
+        if Self::in_range(&wanted) && self.is_free(&wanted) {
+            return Some(hint);
+        }
+        let mut start = MMAP_BASE >> Sv39::PAGE_BITS;
+        for (&region_start, region) in &self.regions {
+            if region_start >= start + pages {
+                break;
+            }
+            start = start.max(region.end);
+        }
+        Self::in_range(&(start..start + pages)).then_some(start)
+    }
+
+    /// 页号区间 `range` 内是否没有任何映射
+    pub fn is_free(&self, range: &Range<usize>) -> bool {
+        // 区域互不重叠，只需检查起点在 `range.end` 之前的最后一个区域
+        let last = self.regions.range(..range.end).next_back();
+        last.map_or(true, |(_, region)| region.end <= range.start)
+    }
+
+    /// 在空闲的页号区间 `range` 建立权限为 `prot` 的映射，`file` 为 `None` 时是匿名映射
+    ///
+    /// 匿名映射立即分配物理页，文件映射等到缺页时再读入。
+    pub fn map(
+        &mut self,
+        range: Range<usize>,
+        prot: usize,
+        file: Option<FileMapping>,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) {
+        if file.is_none() {
+            let flags = vm_flags(prot);
+            // 逐页映射，与 `from_elf` 一致，便于 fork 复制和逐页释放
+            for vpn in range.clone() {
+                let vpn = VPN::<Sv39>::new(vpn);
+                space.map(vpn..vpn + 1, &[], 0, flags);
+            }
+        }
+        self.regions.insert(range.start, Region { end: range.end, prot, file });
+    }
+
+    /// 取消页号区间 `range` 内的映射，不属于任何区域的页忽略；共享映射的脏页先写回文件
+    pub fn unmap(&mut self, range: Range<usize>, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+        self.split(range.start);
+        self.split(range.end);
+        let starts: Vec<usize> = self.regions.range(range).map(|(&start, _)| start).collect();
+        for start in starts {
+            let mut region = self.regions.remove(&start).unwrap();
+            write_back(start, &mut region, space);
+            for vpn in start..region.end {
+                let vpn = VPN::<Sv39>::new(vpn);
+                if present(space, vpn) {
+                    space.unmap(vpn..vpn + 1);
+                }
+            }
+        }
+    }
+
+    /// 页 `vpn` 是否属于共享的文件映射，这样的页在 fork 后与子进程共用物理页
+    pub fn is_shared(&self, vpn: usize) -> bool {
+        self.regions.range(..=vpn).next_back().is_some_and(|(_, region)| {
+            region.end > vpn && region.file.as_ref().is_some_and(|file| file.shared)
+        })
+    }
+
+    /// 调入所有共享文件映射中尚未调入的页，fork 前调用
+    ///
+    /// 否则父子进程以后各自从文件读入这些页，得到不同的物理页，看不到对方的修改。
+    pub fn populate_shared(&mut self, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+        for (&start, region) in self.regions.iter_mut() {
+            if !region.file.as_ref().is_some_and(|file| file.shared) {
+                continue;
+            }
+            for vpn in start..region.end {
+                if !present(space, VPN::new(vpn)) {
+                    page_in(start, region, vpn, space);
+                }
+            }
+        }
+    }
+
+    /// 页号区间 `range` 内是否有以只读方式打开的文件的共享映射，这样的区间不能改为可写
+    pub fn write_denied(&self, range: &Range<usize>) -> bool {
+        let first = self.regions.range(..=range.start).next_back();
+        let first = first.map_or(range.start, |(&start, _)| start);
+        self.regions.range(first..range.end).any(|(_, region)| {
+            region.end > range.start
+                && region.file.as_ref().is_some_and(|file| file.shared && !file.writable)
+        })
+    }
+
+    /// 把页号区间 `range` 的权限改为 `prot`
+    ///
+    /// 区间中有不属于任何区域的页时不做修改，返回 `false`。
+    pub fn protect(
+        &mut self,
+        range: Range<usize>,
+        prot: usize,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) -> bool {
+        if !self.covers(&range) {
+            return false;
+        }
+        self.split(range.start);
+        self.split(range.end);
+        for (&start, region) in self.regions.range_mut(range) {
+            if region.prot != prot {
+                region.prot = prot;
+                let shared = region.file.as_ref().is_some_and(|file| file.shared);
+                for vpn in start..region.end {
+                    let flags = region.page_flags(vpn);
+                    let vpn = VPN::new(vpn);
+                    if present(space, vpn) {
+                        remap(space, vpn, flags, shared);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// 把页号区间 `range` 内共享映射的脏页写回文件
+    ///
+    /// 区间中有不属于任何区域的页时返回 `false`。
+    pub fn sync(
+        &mut self,
+        range: Range<usize>,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) -> bool {
+        if !self.covers(&range) {
+            return false;
+        }
+        self.split(range.start);
+        self.split(range.end);
+        for (&start, region) in self.regions.range_mut(range) {
+            write_back(start, region, space);
+        }
+        true
+    }
+
+    /// 把所有共享映射的脏页写回文件，进程结束时调用
+    pub fn sync_all(&mut self, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+        for (&start, region) in self.regions.iter_mut() {
+            write_back(start, region, space);
+        }
+    }
+
+    /// 处理 mmap 区域内页 `vpn` 的缺页异常，能够处理时返回 `true`（出错的指令可以重新执行）
+    ///
+    /// 文件映射的页第一次访问时从文件读入；共享映射的页第一次写入时记为脏页并开放写权限。
+    /// 不在任何区域内、权限不允许或匿名映射的缺页返回 `false`，由调用者作为 SIGSEGV 处理。
+    pub fn fault(
+        &mut self,
+        vpn: usize,
+        access: Access,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) -> bool {
+        let Some((&start, region)) = self.regions.range_mut(..=vpn).next_back() else {
+            return false;
+        };
+        if region.end <= vpn || !access.allowed(region.prot) {
+            return false;
+        }
+        let Some(file) = &mut region.file else {
+            return false;
+        };
+        let page = VPN::<Sv39>::new(vpn);
+        if !present(space, page) {
+            if file.shared && access == Access::Write {
+                file.dirty.insert(vpn);
+            }
+            page_in(start, region, vpn, space);
+            true
+        } else if file.shared && access == Access::Write && !file.dirty.contains(&vpn) {
+            file.dirty.insert(vpn);
+            let flags = region.page_flags(vpn);
+            remap(space, page, flags, true);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 页号区间 `range` 是否全部属于某些区域
+    fn covers(&self, range: &Range<usize>) -> bool {
+        let mut next = range.start;
+        // 从包含 `range.start` 的区域开始检查
+        let first = self.regions.range(..=range.start).next_back();
+        let first = first.map_or(range.start, |(&start, _)| start);
+        for (&start, region) in self.regions.range(first..range.end) {
+            if region.end <= next {
+                continue;
+            }
+            if start > next {
+                return false;
+            }
+            next = region.end;
+        }
+        next >= range.end
+    }
+
+    /// 把跨越页号 `at` 的区域在 `at` 处一分为二
+    fn split(&mut self, at: usize) {
+        let Some((&start, region)) = self.regions.range_mut(..at).next_back() else {
+            return;
+        };
+        if region.end > at {
+            let file = region.file.as_mut().map(|file| FileMapping {
+                inode: file.inode.clone(),
+                offset: file.offset + (at - start) * PAGE_SIZE,
+                end: file.end,
+                shared: file.shared,
+                writable: file.writable,
+                dirty: file.dirty.split_off(&at),
+            });
+            let tail = Region { end: region.end, prot: region.prot, file };
+            region.end = at;
+            self.regions.insert(at, tail);
+        }
+    }
+}
+
+/// 从文件读入文件映射区域（起始页号 `start`）中的页 `vpn` 并映射
+fn page_in(start: usize, region: &Region, vpn: usize, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+    let file = region.file.as_ref().unwrap();
+    let mut data = [0u8; PAGE_SIZE];
+    // 超出文件末尾的部分读不到数据，保持为 0
+    file.inode.read_at(file.offset + (vpn - start) * PAGE_SIZE, &mut data);
+    let page = VPN::<Sv39>::new(vpn);
+    space.map(page..page + 1, &data, 0, region.page_flags(vpn));
+}
+
+/// 把区域（起始页号 `start`）的脏页写回文件，写回后恢复只读以便记录下一次写入
+fn write_back(start: usize, region: &mut Region, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+    let Some(file) = &mut region.file else {
+        return;
+    };
+    for vpn in core::mem::take(&mut file.dirty) {
+        let page = VPN::<Sv39>::new(vpn);
+        let offset = file.offset + (vpn - start) * PAGE_SIZE;
+        let len = file.end.saturating_sub(offset).min(PAGE_SIZE);
+        let data = space
+            .translate::<[u8; PAGE_SIZE]>(page.base(), unsafe { VmFlags::from_raw(0) })
+            .expect("dirty page not mapped");
+        file.inode.write_at(offset, unsafe { &data.as_ref()[..len] });
+        remap(space, page, vm_flags(region.prot & !PROT_WRITE), true);
+    }
+}
+
+/// 页 `vpn` 是否已映射
+fn present(space: &AddressSpace<Sv39, Sv39Manager>, vpn: VPN<Sv39>) -> bool {
+    space.translate::<u8>(vpn.base(), unsafe { VmFlags::from_raw(0) }).is_some()
+}
+
+/// `PROT_*` 对应的页表权限
+fn vm_flags(prot: usize) -> VmFlags<Sv39> {
+    let mut flags: [u8; 5] = *b"U___V";
+    if prot & (PROT_READ | PROT_WRITE | PROT_EXEC) == 0 {
+        // 去掉 U 位：用户态不可访问，但页表项仍是合法的叶子，物理页得以保留
+        flags = *b"___RV";
+    }
+    if prot & PROT_EXEC != 0 {
+        flags[1] = b'X';
+    }
+    if prot & PROT_WRITE != 0 {
+        flags[2] = b'W';
+        flags[3] = b'R';
+    }
+    if prot & PROT_READ != 0 {
+        flags[3] = b'R';
+    }
+    parse_flags(unsafe { core::str::from_utf8_unchecked(&flags) }).unwrap()
+}
+
+/// 以权限 `flags` 重新映射页 `vpn`，保留页的内容
+///
+/// 共享映射的页（`shared`）可能与 fork 得到的进程共用物理页，原地修改页表项；
+/// 其他页复制内容后重新映射，写时复制的页因此得到自己的副本。
+fn remap(
+    space: &mut AddressSpace<Sv39, Sv39Manager>,
+    vpn: VPN<Sv39>,
+    flags: VmFlags<Sv39>,
+    shared: bool,
+) {
+    if shared {
+        cow::set_flags(space, vpn.val(), flags);
+        return;
+    }
+    let page = space
+        .translate::<[u8; PAGE_SIZE]>(vpn.base(), unsafe { VmFlags::from_raw(0) })
+        .expect("mmap page not mapped");
+    let data = unsafe { *page.as_ref() };
+    space.unmap(vpn..vpn + 1);
+    space.map(vpn..vpn + 1, &data, 0, flags);
+}
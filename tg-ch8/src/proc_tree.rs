@@ -0,0 +1,278 @@
+//! 进程树与作业控制
+//!
+//! `PThreadManager` 的父子关系记录在它内部，父进程先退出时子进程无人接管，
+//! 它们退出后的记录也无人回收。内核在这里自行维护进程树：
+//!
+//! - 进程退出时成为**僵尸**：`Process`（地址空间、文件描述符表）随 `ProcManager::delete` 释放，
+//!   树中只保留 PID 与退出码，直到父进程通过 wait 回收；
+//! - 进程退出时，它的子进程（包括尚未回收的僵尸）**过继**给 initproc，
+//!   initproc 的 wait 循环因此充当整个系统的回收者。
+//!
+//! ## 进程组与会话
+//!
+//! 每个进程属于一个进程组，每个进程组属于一个会话，新进程继承父进程的进程组和会话。
+//! `kill` 可以向整个进程组发送信号；控制台（见 `tty` 模块）记录前台进程组，
+//! Ctrl-C 只发给前台进程组。
+//!
+//! 收到 SIGSTOP 的进程被**停止**：它的线程挂起在这里，直到收到 SIGCONT 或 SIGKILL；
+//! 父进程可以用 `wait4(WUNTRACED)` 得知子进程已停止。
+//!
+//! 进程树与 `PROCESSOR` 一样只在持有 `smp::KERNEL_LOCK` 时访问。
+
+use crate::processor::INITPROC;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use spin::Mutex;
+use tg_task_manage::{ProcId, ThreadId};
+
+/// 进程树中的一个进程
+struct ProcNode {
+    /// 父进程（只有 initproc 没有父进程）
+    parent: Option<ProcId>,
+    /// 子进程（含尚未回收的僵尸）
+    children: BTreeSet<ProcId>,
+    /// 退出码：`Some` 表示已退出、等待父进程回收
+    exit_code: Option<isize>,
+    /// 进程组 ID
+    pgid: ProcId,
+    /// 会话 ID
+    sid: ProcId,
+    /// 进程停止后挂起的线程
+    stopped: Vec<ThreadId>,
+    /// 停止状态尚未通过 `wait4(WUNTRACED)` 报告给父进程
+    stop_pending: bool,
+}
+
+impl ProcNode {
+    #[inline]
+    fn alive(&self) -> bool {
+        self.exit_code.is_none()
+    }
+}
+
+/// wait 查询子进程的结果
+pub enum Reap {
+    /// 回收了一个僵尸子进程：(PID, 退出码)
+    Exited(ProcId, isize),
+    /// 有子进程停止（只在 `untraced` 时报告，每次停止报告一次）
+    Stopped(ProcId),
+    /// 有匹配的子进程，但都还在运行
+    Running,
+    /// 没有匹配的子进程
+    NoChild,
+}
+
+/// 进程树
+pub struct ProcTree {
+    nodes: BTreeMap<ProcId, ProcNode>,
+}
+
+impl ProcTree {
+    const fn new() -> Self {
+        Self { nodes: BTreeMap::new() }
+    }
+
+    /// 登记新进程，继承父进程的进程组和会话
+    ///
+    /// `parent` 不在树中时视为没有父进程，新进程自成一个会话和进程组。
+    pub fn insert(&mut self, pid: ProcId, parent: ProcId) {
+        let (parent, pgid, sid) = match self.nodes.get_mut(&parent) {
+            Some(node) => {
+                node.children.insert(pid);
+                (Some(parent), node.pgid, node.sid)
+            }
+            None => (None, pid, pid),
+        };
+        self.nodes.insert(
+            pid,
+            ProcNode {
+                parent,
+                children: BTreeSet::new(),
+                exit_code: None,
+                pgid,
+                sid,
+                stopped: Vec::new(),
+                stop_pending: false,
+            },
+        );
+    }
+
+    /// 父进程
+    pub fn parent(&self, pid: ProcId) -> Option<ProcId> {
+        self.nodes.get(&pid)?.parent
+    }
+
+    /// 进程组 ID
+    pub fn pgid(&self, pid: ProcId) -> Option<ProcId> {
+        self.nodes.get(&pid).filter(|node| node.alive()).map(|node| node.pgid)
+    }
+
+    /// 会话 ID
+    pub fn sid(&self, pid: ProcId) -> Option<ProcId> {
+        self.nodes.get(&pid).filter(|node| node.alive()).map(|node| node.sid)
+    }
+
+    /// `pid` 所在会话中未退出的进程数（含 `pid` 自身）
+    pub fn session_size(&self, pid: ProcId) -> usize {
+        self.sid(pid).map_or(0, |sid| {
+            self.nodes.values().filter(|node| node.alive() && node.sid == sid).count()
+        })
+    }
+
+    /// 所有未退出的进程
+    pub fn alive(&self) -> Vec<ProcId> {
+        self.nodes.iter().filter(|(_, node)| node.alive()).map(|(&pid, _)| pid).collect()
+    }
+
+    /// 进程组 `pgid` 中所有未退出的进程
+    pub fn group(&self, pgid: ProcId) -> Vec<ProcId> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.alive() && node.pgid == pgid)
+            .map(|(&pid, _)| pid)
+            .collect()
+    }
+
+    /// 会话 `sid` 中是否存在进程组 `pgid`
+    pub fn group_in_session(&self, pgid: ProcId, sid: ProcId) -> bool {
+        self.nodes.values().any(|node| node.alive() && node.pgid == pgid && node.sid == sid)
+    }
+
+    /// setpgid：`caller` 把自己或子进程 `pid` 移入进程组 `pgid`（不存在时以 `pid` 为组长新建）
+    ///
+    /// 目标必须与 `caller` 在同一会话且不是会话首进程；加入已有进程组时该组必须在同一会话中。
+    pub fn setpgid(&mut self, caller: ProcId, pid: ProcId, pgid: ProcId) -> bool {
+        let Some(target) = self.nodes.get(&pid).filter(|node| node.alive()) else {
+            return false;
+        };
+        let session = self.nodes[&caller].sid;
+        let related = pid == caller || target.parent == Some(caller);
+        if !related || target.sid != session || target.sid == pid {
+            return false;
+        }
+        if pgid != pid && !self.group_in_session(pgid, session) {
+            return false;
+        }
+        self.nodes.get_mut(&pid).unwrap().pgid = pgid;
+        true
+    }
+
+    /// setsid：`pid` 新建会话并成为会话首进程和组长；已是组长时失败
+    pub fn setsid(&mut self, pid: ProcId) -> bool {
+        if !self.group(pid).is_empty() {
+            return false;
+        }
+        let node = self.nodes.get_mut(&pid).unwrap();
+        node.pgid = pid;
+        node.sid = pid;
+        true
+    }
+
+    /// 进程 `pid` 的线程 `tid` 因停止信号挂起
+    pub fn stop(&mut self, pid: ProcId, tid: ThreadId) {
+        if let Some(node) = self.nodes.get_mut(&pid) {
+            if node.stopped.is_empty() {
+                node.stop_pending = true;
+            }
+            node.stopped.push(tid);
+        }
+    }
+
+    /// 进程 `pid` 继续运行：返回挂起的线程，由调用者唤醒
+    pub fn resume(&mut self, pid: ProcId) -> Vec<ThreadId> {
+        self.nodes.get_mut(&pid).map_or(Vec::new(), |node| {
+            node.stop_pending = false;
+            core::mem::take(&mut node.stopped)
+        })
+    }
+
+    /// 进程 `pid` 以 `exit_code` 退出：子进程过继给 initproc，自身成为僵尸
+    ///
+    /// 没有父进程的进程（initproc）不会被回收，直接移出进程树。
+    pub fn exit(&mut self, pid: ProcId, exit_code: isize) {
+        let Some(node) = self.nodes.get_mut(&pid) else {
+            return;
+        };
+        node.exit_code = Some(exit_code);
+        node.stopped.clear();
+        node.stop_pending = false;
+        let children = core::mem::take(&mut node.children);
+        let parent = node.parent;
+        if parent.is_none() {
+            self.nodes.remove(&pid);
+        }
+        let reaper = INITPROC.get().copied().filter(|init| *init != pid && self.nodes.contains_key(init));
+        for child in children {
+            if let Some(node) = self.nodes.get_mut(&child) {
+                node.parent = reaper;
+            }
+            match reaper {
+                Some(init) => {
+                    self.nodes.get_mut(&init).unwrap().children.insert(child);
+                }
+                // 没有 initproc 可以接管：已退出的孤儿直接丢弃
+                None => {
+                    if self.nodes.get(&child).is_some_and(|node| !node.alive()) {
+                        self.nodes.remove(&child);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 为 `parent` 回收 `pid` 指定的子进程
+    ///
+    /// `pid` 按 waitpid 的约定：大于 0 为指定子进程，-1 为任意子进程，
+    /// 0 为与 `parent` 同组的子进程，小于 -1 为进程组 `-pid` 中的子进程。
+    /// `untraced` 时同时报告新停止的子进程。
+    pub fn reap(&mut self, parent: ProcId, pid: isize, untraced: bool) -> Reap {
+        let Some(node) = self.nodes.get(&parent) else {
+            return Reap::NoChild;
+        };
+        let group = match pid {
+            0 => Some(node.pgid),
+            pid if pid < -1 => Some(ProcId::from_usize(-pid as usize)),
+            _ => None,
+        };
+        let mut matched = false;
+        let mut found = None;
+        for &child in &node.children {
+            let child_node = &self.nodes[&child];
+            let selected = match (pid, group) {
+                (_, Some(pgid)) => child_node.pgid == pgid,
+                (-1, None) => true,
+                _ => child.get_usize() == pid as usize,
+            };
+            if !selected {
+                continue;
+            }
+            matched = true;
+            if let Some(exit_code) = child_node.exit_code {
+                found = Some(Reap::Exited(child, exit_code));
+                break;
+            }
+            if untraced && child_node.stop_pending {
+                found = Some(Reap::Stopped(child));
+                break;
+            }
+        }
+        match found {
+            Some(Reap::Exited(child, exit_code)) => {
+                self.nodes.get_mut(&parent).unwrap().children.remove(&child);
+                self.nodes.remove(&child);
+                Reap::Exited(child, exit_code)
+            }
+            Some(Reap::Stopped(child)) => {
+                self.nodes.get_mut(&child).unwrap().stop_pending = false;
+                Reap::Stopped(child)
+            }
+            _ if matched => Reap::Running,
+            _ => Reap::NoChild,
+        }
+    }
+}
+
+/// 全局进程树
+pub static PROC_TREE: Mutex<ProcTree> = Mutex::new(ProcTree::new());
@@ -24,15 +24,24 @@
 //! - 最后结合 `processor.rs` 看线程生命周期与进程资源回收的关系。
 
 use crate::{
-    build_flags, fs::Fd, map_portal, parse_flags, processor::ProcessorInner, Sv39, Sv39Manager,
-    PROCESSOR,
+    build_flags, cow,
+    fs::Fd,
+    map_portal,
+    mmap::{Access, MemoryMap, MMAP_BASE},
+    parse_flags,
+    rlimit::{ResourceLimits, RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_STACK},
+    smp::HARTS,
+    thread_stack::ThreadStacks,
+    Sv39, Sv39Manager, PROCESSOR,
 };
-use alloc::{alloc::alloc_zeroed, boxed::Box, sync::Arc, vec::Vec};
-use core::alloc::Layout;
+use alloc::{
+    alloc::alloc_zeroed, boxed::Box, collections::BTreeSet, string::String, sync::Arc, vec::Vec,
+};
+use core::{alloc::Layout, ptr::NonNull};
 use spin::Mutex;
 use tg_kernel_context::{foreign::ForeignContext, LocalContext};
 use tg_kernel_vm::{
-    page_table::{MmuMeta, VAddr, PPN, VPN},
+    page_table::{MmuMeta, VAddr, VmFlags, PPN, VPN},
     AddressSpace,
 };
 use tg_signal::Signal;
@@ -47,6 +56,56 @@ use xmas_elf::{
 const PAGE_SIZE: usize = 4096;
 const PAGE_MASK: usize = PAGE_SIZE - 1;
 
+/// 用户栈顶（主线程初始 sp 的上界）
+pub const USER_STACK_TOP: usize = 1 << 38;
+/// 为主线程栈预留的页数（16384 页 = 64 MiB），也是 `RLIMIT_STACK` 的硬限制
+///
+/// 栈按需增长：只有访问过的页才映射，见 `Process::grow_stack`。
+pub const USER_STACK_PAGES: usize = 1 << 14;
+/// 主线程栈的默认大小上限（`RLIMIT_STACK` 的默认软限制，8 MiB）
+pub const DEFAULT_STACK_SIZE: usize = 8 << 20;
+/// 主线程栈预留区域下方不映射任何页的间隔页数，线程栈从间隔下方开始分配
+pub const STACK_GUARD_PAGES: usize = 256;
+/// exec 参数（字符串与指针数组）的总字节数上限
+pub const ARG_MAX: usize = 64 * 1024;
+
+/// 位置无关可执行文件的加载基址
+pub const PIE_BASE: usize = 0x1000_0000;
+
+/// 线程默认优先级
+pub const DEFAULT_PRIORITY: usize = 16;
+/// 线程优先级的上限（stride 调度的 `BIG_STRIDE`），更大的优先级按它计，步长至少为 1
+pub const MAX_PRIORITY: usize = 1 << 32;
+
+/// 线程最近一次离开 CPU 的原因，供调度器调整策略
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SwitchReason {
+    /// 时间片耗尽，被时钟中断抢占
+    Preempted,
+    /// 主动让出（系统调用返回后重新排队）
+    Voluntary,
+    /// 等待同步原语或 I/O 而阻塞
+    Blocked,
+}
+
+/// CPU 使用统计（时间单位为时钟周期）
+///
+/// 线程和进程各有一份：每次 Trap 返回内核后同时累加到当前线程及其所属进程，
+/// 因此进程的统计包含它已经退出的线程。
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CpuUsage {
+    /// 用户态运行时间
+    pub utime: u64,
+    /// 内核态（处理 Trap）时间
+    pub stime: u64,
+    /// 被调度上 CPU 的次数
+    pub nswitch: u64,
+    /// 主动让出 CPU 的次数（阻塞或 `sched_yield`）
+    pub nvcsw: u64,
+    /// 被动让出 CPU 的次数（时钟中断或核间中断）
+    pub nivcsw: u64,
+}
+
 /// 线程（执行单元）
 ///
 /// 每个线程有独立的 TID 和上下文（寄存器状态、satp）。
@@ -56,6 +115,25 @@ pub struct Thread {
     pub tid: ThreadId,
     /// 执行上下文（包含 LocalContext + satp）
     pub context: ForeignContext,
+    /// 调度优先级（≥ 2），决定 stride 调度中每次前进的步长
+    pub priority: usize,
+    /// stride 调度的累计行程值（pass）
+    pub stride: usize,
+    /// 多级反馈队列中所处的层级（0 为最高优先级）
+    pub level: usize,
+    /// 最近一次离开 CPU 的原因
+    pub last_switch: SwitchReason,
+    /// CPU 使用统计
+    pub usage: CpuUsage,
+    /// 已被同进程的 exec 结束：线程正在其他核上运行，回到内核后直接退出
+    pub killed: bool,
+    /// 所属进程被停止，挂起在进程树中：只有 SIGCONT 或 SIGKILL 能唤醒
+    pub stopped: bool,
+    /// 阻塞在可重新执行的系统调用中（见 `syscall_ext::Restart`），可以被任何送达的信号唤醒；
+    /// 阻塞在同步原语中的线程只被终止进程的信号唤醒
+    pub interruptible: bool,
+    /// nanosleep 的唤醒时刻：睡眠中重新执行 nanosleep 时据此判断是否被信号提前唤醒
+    pub sleep_deadline: Option<u64>,
 }
 
 impl Thread {
@@ -64,6 +142,15 @@ impl Thread {
         Self {
             tid: ThreadId::new(),
             context: ForeignContext { context, satp },
+            priority: DEFAULT_PRIORITY,
+            stride: 0,
+            level: 0,
+            last_switch: SwitchReason::Voluntary,
+            usage: CpuUsage::default(),
+            killed: false,
+            stopped: false,
+            interruptible: false,
+            sleep_deadline: None,
         }
     }
 }
@@ -77,6 +164,12 @@ pub struct Process {
     pub pid: ProcId,
     /// 地址空间（所有线程共享）
     pub address_space: AddressSpace<Sv39, Sv39Manager>,
+    /// mmap 建立的区域及其权限
+    pub mmap: MemoryMap,
+    /// 堆底地址（程序各段之后的第一页）
+    pub heap_bottom: usize,
+    /// 当前堆顶地址（program break，通过 sbrk 调整）
+    pub program_brk: usize,
     /// 文件描述符表（所有线程共享）
     pub fd_table: Vec<Option<Mutex<Fd>>>,
     /// 信号处理器
@@ -87,41 +180,70 @@ pub struct Process {
     pub mutex_list: Vec<Option<Arc<dyn MutexTrait>>>,
     /// 条件变量列表（**本章新增**，所有线程共享）
     pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// 所有线程（含已退出线程）的 CPU 使用统计之和
+    pub usage: CpuUsage,
+    /// 资源限制（fork 时继承）
+    pub rlimits: ResourceLimits,
+    /// `thread_create` 创建的线程的用户栈
+    pub stacks: ThreadStacks,
+    /// 程序的 TLS 模板（没有 `PT_TLS` 段时为 `None`）
+    pub tls: Option<TlsTemplate>,
+    /// 可以被 `waittid` 等待的线程（含已退出、尚未回收的线程）
+    pub joinable: BTreeSet<ThreadId>,
+    /// 已分离的存活线程：退出时自动回收
+    pub detached: BTreeSet<ThreadId>,
+    /// 进程整体的退出状态（被信号终止时设置），最后一个线程退出时记入进程树
+    pub exit_status: Option<isize>,
 }
 
 impl Process {
-    /// exec：替换当前进程的地址空间和主线程上下文
+    /// exec：用 `image`（由 `from_elf` 新建）替换当前进程的地址空间，调用线程换用 `thread` 的上下文
     ///
-    /// 注意：只支持单线程进程执行 exec
-    pub fn exec(&mut self, elf: ElfFile) {
-        let (mut proc, thread) = Process::from_elf(elf).unwrap();
-        core::mem::swap(&mut self.address_space, &mut proc.address_space);
-        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-        unsafe {
-            let pthreads = (*processor).get_thread(self.pid).unwrap();
-            (*processor).get_task(pthreads[0]).unwrap().context = thread.context;
-        }
+    /// 调用者需先用 `processor::kill_siblings` 结束本进程的其他线程，
+    /// 它们的用户栈随旧地址空间一起在 `image` 析构时释放。
+    ///
+    /// 信号处理函数的地址在新程序中失效，信号配置换成 `image` 的默认配置，只保留信号掩码。
+    pub fn exec(&mut self, mut image: Process, thread: Thread) {
+        core::mem::swap(&mut self.address_space, &mut image.address_space);
+        core::mem::swap(&mut self.mmap, &mut image.mmap);
+        self.heap_bottom = image.heap_bottom;
+        self.program_brk = image.program_brk;
+        image.signal.update_mask(self.signal.update_mask(0));
+        core::mem::swap(&mut self.signal, &mut image.signal);
+        core::mem::swap(&mut self.stacks, &mut image.stacks);
+        core::mem::swap(&mut self.tls, &mut image.tls);
+        // 其他线程已经结束，只剩调用线程
+        let current = PROCESSOR.get_mut().current().unwrap();
+        current.context = thread.context;
+        self.joinable = BTreeSet::from([current.tid]);
+        self.detached.clear();
     }
 
-    /// fork：创建子进程（复制地址空间和主线程上下文）
+    /// fork：创建子进程（复制地址空间和调用线程的上下文）
     ///
-    /// 子进程继承父进程的地址空间（深拷贝）、文件描述符和信号配置。
+    /// 子进程只有一个线程，从调用线程的 fork 返回处继续执行；其他线程不会被复制。
+    /// 子进程继承父进程的地址空间（写时复制，见 `cow` 模块）、文件描述符和信号配置。
     /// 同步原语列表不继承（子进程创建空的列表）。
     pub fn fork(&mut self) -> Option<(Self, Thread)> {
         let pid = ProcId::new();
-        // 深拷贝地址空间
-        let parent_addr_space = &self.address_space;
+        // 与父进程共享物理页，双方可写的页都变为只读的 COW 页；共享文件映射先全部调入，父子共用
+        self.mmap.populate_shared(&mut self.address_space);
         let mut address_space: AddressSpace<Sv39, Sv39Manager> = AddressSpace::new();
-        parent_addr_space.cloneself(&mut address_space);
+        cow::share(&self.address_space, &mut address_space, &self.mmap);
         map_portal(&address_space);
-        // 复制主线程上下文
-        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-        let pthreads = unsafe { (*processor).get_thread(self.pid).unwrap() };
-        let context = unsafe {
-            (*processor).get_task(pthreads[0]).unwrap().context.context.clone()
-        };
+        // 父进程在其他核上运行的线程可能缓存着可写的旧表项
+        if let Some(threads) = PROCESSOR.get_mut().get_thread(self.pid) {
+            HARTS.flush_tlb(threads);
+        }
+        // 复制调用线程的上下文
+        let parent_thread = PROCESSOR.get_mut().current().unwrap();
+        let context = parent_thread.context.context.clone();
         let satp = (8 << 60) | address_space.root_ppn().val();
-        let thread = Thread::new(satp, context);
+        let mut thread = Thread::new(satp, context);
+        // 子进程主线程继承父线程的调度优先级
+        thread.priority = parent_thread.priority;
+        // 调用线程的栈在子进程中归新线程所有，父进程其他线程的栈不再需要
+        let stacks = self.stacks.fork(parent_thread.tid, thread.tid, &mut address_space);
         // 复制文件描述符表
         let new_fd_table: Vec<Option<Mutex<Fd>>> = self.fd_table
             .iter()
@@ -131,12 +253,22 @@ impl Process {
             Self {
                 pid,
                 address_space,
+                mmap: self.mmap.clone(),
+                heap_bottom: self.heap_bottom,
+                program_brk: self.program_brk,
                 fd_table: new_fd_table,
                 signal: self.signal.from_fork(),
                 // 子进程的同步原语列表初始为空
                 semaphore_list: Vec::new(),
                 mutex_list: Vec::new(),
                 condvar_list: Vec::new(),
+                usage: CpuUsage::default(),
+                rlimits: self.rlimits.inherit(),
+                stacks,
+                tls: self.tls.clone(),
+                joinable: BTreeSet::from([thread.tid]),
+                detached: BTreeSet::new(),
+                exit_status: None,
             },
             thread,
         ))
@@ -144,22 +276,41 @@ impl Process {
 
     /// 从 ELF 文件创建进程和主线程
     ///
-    /// 解析 ELF 段，建立地址空间，分配用户栈，创建初始上下文。
-    pub fn from_elf(elf: ElfFile) -> Option<(Self, Thread)> {
-        let entry = match elf.header.pt2 {
-            HeaderPt2::Header64(pt2)
-                if pt2.type_.as_type() == header::Type::Executable
-                    && pt2.machine.as_machine() == Machine::RISC_V =>
-            { pt2.entry_point as usize }
+    /// 解析 ELF 段，建立地址空间，分配用户栈并在栈上布置 `argv`、`envp`，创建初始上下文。
+    ///
+    /// 位置无关可执行文件（`ET_DYN`）整体平移到 `PIE_BASE` 加载，并应用其中的相对重定位；
+    /// 需要动态链接器（带 `PT_INTERP`）的程序不能加载。
+    pub fn from_elf(elf: ElfFile, argv: &[String], envp: &[String]) -> Option<(Self, Thread)> {
+        let (entry, bias) = match elf.header.pt2 {
+            HeaderPt2::Header64(pt2) if pt2.machine.as_machine() == Machine::RISC_V => {
+                match pt2.type_.as_type() {
+                    header::Type::Executable => (pt2.entry_point as usize, 0),
+                    header::Type::SharedObject => (PIE_BASE + pt2.entry_point as usize, PIE_BASE),
+                    _ => None?,
+                }
+            }
             _ => None?,
         };
+        if elf
+            .program_iter()
+            .any(|program| matches!(program.get_type(), Ok(program::Type::Interp)))
+        {
+            return None;
+        }
 
+        let tls = TlsTemplate::from_elf(&elf);
+        // TLS 块放在主线程栈顶，不能占用过多的栈
+        if tls.as_ref().is_some_and(|tls| tls.footprint() > DEFAULT_STACK_SIZE / 2) {
+            return None;
+        }
         let mut address_space = AddressSpace::new();
+        // 堆紧接在最高的段之后
+        let mut heap_bottom = 0;
         for program in elf.program_iter() {
             if !matches!(program.get_type(), Ok(program::Type::Load)) { continue; }
             let off_file = program.offset() as usize;
             let len_file = program.file_size() as usize;
-            let off_mem = program.virtual_addr() as usize;
+            let off_mem = bias + program.virtual_addr() as usize;
             let len_mem = program.mem_size() as usize;
             
             let mut flags: [u8; 5] = *b"U___V";
@@ -170,6 +321,7 @@ impl Process {
 
             let vaddr_start = VAddr::new(off_mem).floor();
             let vaddr_end = VAddr::new(off_mem + len_mem).ceil();
+            heap_bottom = heap_bottom.max(vaddr_end.base().val());
             
             let mut curr_vaddr = vaddr_start;
             while curr_vaddr < vaddr_end {
@@ -203,61 +355,364 @@ impl Process {
                 curr_vaddr += 1;
             }
         }
-        // 分配 128 页用户栈 (512 KiB)，逐页映射以便正确生命周期管理和 fork 复制
-        let stack_vpn_start = VPN::<Sv39>::new((1 << 26) - 128);
-        let stack_vpn_end = VPN::<Sv39>::new(1 << 26);
-        let mut curr_vpn = stack_vpn_start;
-        let zero_page = [0u8; 4096];
-        while curr_vpn < stack_vpn_end {
-            address_space.map(
-                curr_vpn .. curr_vpn + 1,
-                &zero_page,
-                0,
-                build_flags("U_WRV"),
-            );
-            curr_vpn += 1;
+        if bias != 0 {
+            relocate(&elf, &address_space, bias)?;
         }
+        // 用户栈按需分配：这里只映射布置 TLS 和参数时写到的页（见 `copy_to_user`），
+        // 其余的页在第一次访问时由缺页异常映射
         map_portal(&address_space);
+        // 主线程的 TLS 块位于用户栈最顶端，参数布置在它下方
+        let tp = tls.as_ref().map(|tls| tls.place(&mut address_space, USER_STACK_TOP));
+        let top = tp.unwrap_or(USER_STACK_TOP);
+        let (sp, argv_base, envp_base) = push_args(&mut address_space, top, argv, envp, entry)?;
         let satp = (8 << 60) | address_space.root_ppn().val();
         let mut context = LocalContext::user(entry);
-        *context.sp_mut() = 1 << 38;
+        *context.sp_mut() = sp;
+        // 同时通过寄存器传递 argc、argv、envp，用户态入口可以直接作为参数使用
+        *context.a_mut(0) = argv.len();
+        *context.a_mut(1) = argv_base;
+        *context.a_mut(2) = envp_base;
+        if let Some(tp) = tp {
+            *context.x_mut(4) = tp; // x4 即 tp
+        }
         let thread = Thread::new(satp, context);
 
         Some((
             Self {
                 pid: ProcId::new(),
                 address_space,
+                mmap: MemoryMap::new(),
+                heap_bottom,
+                program_brk: heap_bottom,
                 fd_table: vec![
                     // stdin
-                    Some(Mutex::new(Fd::Empty { read: true, write: false })),
+                    Some(Mutex::new(Fd::Console { read: true, write: false })),
                     // stdout
-                    Some(Mutex::new(Fd::Empty { read: false, write: true })),
+                    Some(Mutex::new(Fd::Console { read: false, write: true })),
                     // stderr
-                    Some(Mutex::new(Fd::Empty { read: false, write: true })),
+                    Some(Mutex::new(Fd::Console { read: false, write: true })),
                 ],
                 signal: Box::new(SignalImpl::new()),
                 semaphore_list: Vec::new(),
                 mutex_list: Vec::new(),
                 condvar_list: Vec::new(),
+                usage: CpuUsage::default(),
+                rlimits: ResourceLimits::new(),
+                stacks: ThreadStacks::new(),
+                joinable: BTreeSet::from([thread.tid]),
+                detached: BTreeSet::new(),
+                exit_status: None,
+                tls,
             },
             thread,
         ))
     }
+
+    /// 已映射的用户页数
+    pub fn mapped_pages(&self) -> usize {
+        self.address_space.areas.iter().map(|range| range.end.val() - range.start.val()).sum()
+    }
+
+    /// 处理用户态访问 `addr` 产生的缺页异常，能够处理时返回 `true`（出错的指令可以重新执行）
+    ///
+    /// 依次尝试写时复制、mmap 文件映射的调页和主线程栈的增长；
+    /// 不能处理的缺页由调用者作为 SIGSEGV 交给进程。
+    pub fn handle_page_fault(&mut self, addr: usize, access: Access) -> bool {
+        let vpn = addr >> Sv39::PAGE_BITS;
+        (access == Access::Write && cow::fault(&mut self.address_space, vpn))
+            || self.mmap.fault(vpn, access, &mut self.address_space)
+            || (access != Access::Execute && self.grow_stack(vpn))
+    }
+
+    /// 内核代替进程访问用户内存 `[addr, addr + len)` 前调入其中的页，全部就绪时返回 `true`
+    ///
+    /// 按 `access` 所需权限翻译失败的页交给 [`Self::handle_page_fault`]，与进程自己访问时一样
+    /// 解除写时复制、从文件调入或增长栈；遇到不能处理的页即停止，前面的页保持就绪。
+    pub fn fault_in(&mut self, addr: usize, len: usize, access: Access) -> bool {
+        let Some(last) = addr.checked_add(len.max(1) - 1) else {
+            return false;
+        };
+        let flags = match access {
+            Access::Write => build_flags("W_V"),
+            _ => build_flags("RV"),
+        };
+        (addr >> Sv39::PAGE_BITS..=last >> Sv39::PAGE_BITS).all(|vpn| {
+            self.address_space.translate::<u8>(VPN::<Sv39>::new(vpn).base(), flags).is_some()
+                || self.handle_page_fault(vpn << Sv39::PAGE_BITS, access)
+        })
+    }
+
+    /// 内核读用户内存：调入 `addr` 处的 `T` 所在的页，再按可读权限翻译
+    pub fn translate<T>(&mut self, addr: usize) -> Option<NonNull<T>> {
+        if !self.fault_in(addr, core::mem::size_of::<T>(), Access::Read) {
+            return None;
+        }
+        self.address_space.translate(VAddr::new(addr), build_flags("RV"))
+    }
+
+    /// 内核写用户内存：调入 `addr` 处的 `T` 所在的页并解除写时复制，再按可写权限翻译
+    pub fn translate_mut<T>(&mut self, addr: usize) -> Option<NonNull<T>> {
+        if !self.fault_in(addr, core::mem::size_of::<T>(), Access::Write) {
+            return None;
+        }
+        self.address_space.translate(VAddr::new(addr), build_flags("W_V"))
+    }
+
+    /// 主线程栈中尚未映射的页 `vpn` 第一次被访问：映射一个清零的页
+    ///
+    /// 栈自 `USER_STACK_TOP` 向下最多增长到 `RLIMIT_STACK` 的软限制，
+    /// 超出限制或 `RLIMIT_AS` 时返回 `false`，进程收到 SIGSEGV。
+    fn grow_stack(&mut self, vpn: usize) -> bool {
+        let top = VAddr::<Sv39>::new(USER_STACK_TOP).floor().val();
+        let limit = self.rlimits.get(RLIMIT_STACK).unwrap().cur >> Sv39::PAGE_BITS;
+        if vpn >= top || vpn < top - limit.min(USER_STACK_PAGES) || !self.can_map(1) {
+            return false;
+        }
+        let vpn = VPN::<Sv39>::new(vpn);
+        let any = unsafe { VmFlags::from_raw(0) };
+        if self.address_space.translate::<u8>(vpn.base(), any).is_some() {
+            return false;
+        }
+        self.address_space.map(vpn..vpn + 1, &[], 0, build_flags("U_WRV"));
+        true
+    }
+
+    /// 修改程序 break 位置（实现 sbrk 系统调用），返回旧的 break，失败时返回 `None`
+    ///
+    /// 堆不能低于 `heap_bottom`，也不能进入 mmap 区域；新增的页立即映射为清零的页，
+    /// 受 `RLIMIT_AS` 限制。收缩时取消映射 break 以上的整页。
+    pub fn change_program_brk(&mut self, size: isize) -> Option<usize> {
+        let old_brk = self.program_brk;
+        let new_brk = old_brk.checked_add_signed(size)?;
+        if new_brk < self.heap_bottom || new_brk > MMAP_BASE {
+            return None;
+        }
+        let old_end = VAddr::<Sv39>::new(old_brk).ceil();
+        let new_end = VAddr::<Sv39>::new(new_brk).ceil();
+        if new_end > old_end {
+            if !self.can_map(new_end.val() - old_end.val()) {
+                return None;
+            }
+            // 逐页映射，与其他用户页一样以页为单位共享和释放
+            for vpn in old_end.val()..new_end.val() {
+                let vpn = VPN::new(vpn);
+                self.address_space.map(vpn..vpn + 1, &[], 0, build_flags("U_WRV"));
+            }
+        } else {
+            for vpn in new_end.val()..old_end.val() {
+                let vpn = VPN::new(vpn);
+                self.address_space.unmap(vpn..vpn + 1);
+            }
+        }
+        self.program_brk = new_brk;
+        Some(old_brk)
+    }
+
+    /// 能否再映射 `pages` 页而不超过 `RLIMIT_AS`
+    pub fn can_map(&self, pages: usize) -> bool {
+        self.rlimits.allows(RLIMIT_AS, self.mapped_pages() * PAGE_SIZE, pages * PAGE_SIZE)
+    }
+
+    /// 能否再打开 `count` 个文件描述符而不超过 `RLIMIT_NOFILE`
+    pub fn can_open(&self, count: usize) -> bool {
+        let open = self.fd_table.iter().filter(|fd| fd.is_some()).count();
+        self.rlimits.allows(RLIMIT_NOFILE, open, count)
+    }
+}
+
+/// 程序的 TLS 模板：ELF `PT_TLS` 段
+///
+/// 每个线程有一个 TLS 块，由模板复制初值（`.tdata`），其余部分（`.tbss`）为 0。
+/// RISC-V 采用 TLS variant I 且 TCB 大小为 0：线程的 `tp` 直接指向 TLS 块起始处。
+/// TLS 块放在线程用户栈的顶端，随栈一起分配和释放（主线程随地址空间，其他线程在 `waittid` 回收时）。
+#[derive(Clone)]
+pub struct TlsTemplate {
+    /// 初值（`.tdata`）
+    init: Vec<u8>,
+    /// TLS 块的字节数（`.tdata` + `.tbss`）
+    size: usize,
+    /// 对齐要求
+    align: usize,
+}
+
+impl TlsTemplate {
+    /// 读取 ELF 的 `PT_TLS` 段，没有时返回 `None`
+    fn from_elf(elf: &ElfFile) -> Option<Self> {
+        let program = elf
+            .program_iter()
+            .find(|program| matches!(program.get_type(), Ok(program::Type::Tls)))?;
+        let offset = program.offset() as usize;
+        let init = elf.input.get(offset..offset + program.file_size() as usize)?.to_vec();
+        Some(Self {
+            init,
+            size: program.mem_size() as usize,
+            align: (program.align() as usize).max(16),
+        })
+    }
+
+    /// TLS 块最多占用的字节数（含对齐）
+    pub fn footprint(&self) -> usize {
+        self.size + self.align
+    }
+
+    /// 在 `top` 下方布置一个 TLS 块并复制初值，返回线程的 `tp`（目标页必须为 0 或尚未映射）
+    pub fn place(&self, space: &mut AddressSpace<Sv39, Sv39Manager>, top: usize) -> usize {
+        let tp = (top - self.size) & !(self.align - 1);
+        copy_to_user(space, tp, &self.init);
+        tp
+    }
+}
+
+/// 对加载到 `bias` 处的位置无关可执行文件应用 `PT_DYNAMIC` 中的重定位
+///
+/// 静态链接的 PIE 只含 `R_RISCV_RELATIVE`：把 `bias + addend` 写到 `bias + offset`。
+/// 出现需要符号解析的其他类型时返回 `None`。
+fn relocate(elf: &ElfFile, space: &AddressSpace<Sv39, Sv39Manager>, bias: usize) -> Option<()> {
+    const DT_NULL: usize = 0;
+    const DT_RELA: usize = 7;
+    const DT_RELASZ: usize = 8;
+    const DT_RELAENT: usize = 9;
+    const DT_REL: usize = 17;
+    const DT_RELR: usize = 36;
+    const R_RISCV_NONE: usize = 0;
+    const R_RISCV_RELATIVE: usize = 3;
+    let word = |bytes: &[u8], i: usize| {
+        usize::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap())
+    };
+
+    let Some(dynamic) =
+        elf.program_iter().find(|program| matches!(program.get_type(), Ok(program::Type::Dynamic)))
+    else {
+        return Some(());
+    };
+    let offset = dynamic.offset() as usize;
+    let dynamic = elf.input.get(offset..offset + dynamic.file_size() as usize)?;
+    let (mut rela, mut relasz, mut relaent) = (None, 0, 24);
+    for entry in dynamic.chunks_exact(16) {
+        match word(entry, 0) {
+            DT_NULL => break,
+            DT_RELA => rela = Some(word(entry, 1)),
+            DT_RELASZ => relasz = word(entry, 1),
+            DT_RELAENT => relaent = word(entry, 1),
+            // 没有隐式加数的格式在 RISC-V 上不会出现
+            DT_REL | DT_RELR => return None,
+            _ => {}
+        }
+    }
+    let Some(rela) = rela else {
+        return Some(());
+    };
+    // 重定位表的地址是链接时的虚拟地址，换算成文件偏移读取
+    let offset = elf.program_iter().find_map(|program| {
+        let vaddr = program.virtual_addr() as usize;
+        (matches!(program.get_type(), Ok(program::Type::Load))
+            && (vaddr..vaddr + program.file_size() as usize).contains(&rela))
+        .then(|| program.offset() as usize + rela - vaddr)
+    })?;
+    let table = elf.input.get(offset..offset + relasz)?;
+    if relaent < 24 {
+        return None;
+    }
+    for entry in table.chunks_exact(relaent) {
+        let (offset, info, addend) = (word(entry, 0), word(entry, 1), word(entry, 2));
+        match info & 0xffff_ffff {
+            R_RISCV_NONE => {}
+            R_RISCV_RELATIVE => {
+                // 目标可能在只读页中，也可能跨页，逐字节写入
+                let value = bias.wrapping_add(addend).to_le_bytes();
+                for (i, byte) in value.into_iter().enumerate() {
+                    let va = VAddr::new(bias + offset + i);
+                    let ptr = space.translate::<u8>(va, build_flags("U___V"))?;
+                    unsafe { *ptr.as_ptr() = byte };
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+/// 按 RISC-V System V ABI 在用户栈上布置进程参数
+///
+/// 自 `top` 向低地址依次为：参数与环境变量字符串、16 字节对齐填充、
+/// auxv（`AT_PAGESZ`、`AT_ENTRY`、`AT_NULL`）、`envp[]` + NULL、`argv[]` + NULL、`argc`。
+/// 返回 (sp, argv, envp) 的用户地址，sp 指向 `argc`；参数总量超过 `ARG_MAX` 时返回 `None`。
+fn push_args(
+    space: &mut AddressSpace<Sv39, Sv39Manager>,
+    top: usize,
+    argv: &[String],
+    envp: &[String],
+    entry: usize,
+) -> Option<(usize, usize, usize)> {
+    const AT_NULL: usize = 0;
+    const AT_PAGESZ: usize = 6;
+    const AT_ENTRY: usize = 9;
+    let word = core::mem::size_of::<usize>();
+
+    let strings: usize = argv.iter().chain(envp).map(|s| s.len() + 1).sum();
+    let vectors = (1 + argv.len() + 1 + envp.len() + 1 + 6) * word;
+    if strings + vectors > ARG_MAX {
+        return None;
+    }
+
+    // 字符串区
+    let mut top = top;
+    let mut push_str = |s: &String| {
+        top -= s.len() + 1;
+        copy_to_user(space, top, s.as_bytes());
+        copy_to_user(space, top + s.len(), &[0]);
+        top
+    };
+    let argv_ptrs: Vec<usize> = argv.iter().map(&mut push_str).collect();
+    let envp_ptrs: Vec<usize> = envp.iter().map(&mut push_str).collect();
+
+    // 指针区：argc | argv[] | NULL | envp[] | NULL | auxv
+    let mut words: Vec<usize> = Vec::with_capacity(vectors / word);
+    words.push(argv.len());
+    words.extend(&argv_ptrs);
+    words.push(0);
+    words.extend(&envp_ptrs);
+    words.push(0);
+    words.extend([AT_PAGESZ, PAGE_SIZE, AT_ENTRY, entry, AT_NULL, 0]);
+
+    let sp = (top - vectors) & !0xf;
+    for (i, w) in words.iter().enumerate() {
+        copy_to_user(space, sp + i * word, &w.to_ne_bytes());
+    }
+    let argv_base = sp + word;
+    let envp_base = argv_base + (argv.len() + 1) * word;
+    Some((sp, argv_base, envp_base))
+}
+
+/// 把 `data` 复制到新地址空间 `space` 的栈上 `addr` 处，目标页尚未映射时先映射一个清零的页
+fn copy_to_user(space: &mut AddressSpace<Sv39, Sv39Manager>, addr: usize, data: &[u8]) {
+    let mut done = 0;
+    while done < data.len() {
+        let va = addr + done;
+        let len = (data.len() - done).min(PAGE_SIZE - (va & PAGE_MASK));
+        let vpn = VAddr::<Sv39>::new(va).floor();
+        if space.translate::<u8>(vpn.base(), build_flags("W_V")).is_none() {
+            space.map(vpn..vpn + 1, &[], 0, build_flags("U_WRV"));
+        }
+        let ptr = space
+            .translate::<u8>(VAddr::new(va), build_flags("W_V"))
+            .unwrap();
+        unsafe { core::ptr::copy_nonoverlapping(data[done..].as_ptr(), ptr.as_ptr(), len) };
+        done += len;
+    }
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
-        use alloc::alloc::dealloc;
-        use core::alloc::Layout;
         use tg_kernel_vm::page_table::{Sv39, VmFlags};
         
+        // 共享文件映射的脏页先写回文件（exec 换下的旧映射也在这里写回）
+        self.mmap.sync_all(&mut self.address_space);
         for range in &self.address_space.areas {
             let count = range.end.val() - range.start.val();
-            let layout = unsafe {
-                Layout::from_size_align_unchecked(count << Sv39::PAGE_BITS, 1 << Sv39::PAGE_BITS)
-            };
             if let Some(ptr) = self.address_space.translate::<u8>(range.start.base(), unsafe { VmFlags::from_raw(0) }) {
-                unsafe { dealloc(ptr.as_ptr(), layout); }
+                // 与 fork 出的进程共享的页只减少引用计数
+                Sv39Manager::release(PPN::new(ptr.as_ptr() as usize >> Sv39::PAGE_BITS), count);
             }
         }
     }
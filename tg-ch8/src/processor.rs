@@ -17,10 +17,41 @@
 //! - 先看 `ProcessorInner` 类型别名：先建立“统一入口，双层实体”的心智模型；
 //! - 再看 `ThreadManager` 与 `ProcManager` 的 `Manage` 实现：理解两层对象如何独立维护；
 //! - 最后看 `Schedule<ThreadId>`：明确调度粒度已经从进程切换为线程。
+//!
+//! ## stride 调度
+//!
+//! `ThreadManager` 把线程表和就绪队列交给调度策略 `Policy`，默认为 stride 调度（`Stride`）：
+//! 每个线程维护累计行程 `stride`，每次被调度后前进 `BIG_STRIDE / priority`。
+//! `fetch` 总是选择行程最小的就绪线程，因此线程获得的 CPU 份额与其优先级成正比。
+//!
+//! 启用 `mlfq` feature 时，调度策略改由 `crate::mlfq` 提供（多级反馈队列）；
+//! 启用 `fifo` feature 时改由 `crate::fifo` 提供（先来先服务）。两者不能同时启用。
+//!
+//! ## 多核
+//!
+//! 所有核共享同一个 `PROCESSOR` 和就绪队列，访问前必须持有 `smp::KERNEL_LOCK`。
+//! 线程实体用 `Box` 存放：线程在其他核上运行时，传送门仍持有其上下文的地址，
+//! 线程表的增删不能移动它。
+//!
+//! 每个核的当前线程记录在 `Hart::thread` 中；`PThreadManager` 内部只有一个所有核共享的
+//! `current`，持有大内核锁的核用 `Processor::restore_current` 把它设回自己的线程。
 
-use crate::process::{Process, Thread};
-use alloc::collections::{BTreeMap, VecDeque};
+use crate::{
+    coredump,
+    proc_tree::PROC_TREE,
+    process::{Process, SwitchReason, Thread, MAX_PRIORITY},
+    smp::{self, HARTS},
+};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+use alloc::collections::VecDeque;
 use core::cell::UnsafeCell;
+use spin::{Mutex, Once};
+use tg_signal::SignalNo;
 use tg_task_manage::{Manage, PThreadManager, ProcId, Schedule, ThreadId};
 
 /// 处理器内部类型（双层管理器）
@@ -29,6 +60,8 @@ pub type ProcessorInner = PThreadManager<Process, Thread, ThreadManager, ProcMan
 /// 全局处理器包装（通过 `UnsafeCell` 允许内部可变）
 pub struct Processor {
     inner: UnsafeCell<ProcessorInner>,
+    /// 下一次 `fetch` 必须返回的线程（见 `switch_to`）
+    switch: UnsafeCell<Option<ThreadId>>,
 }
 
 unsafe impl Sync for Processor {}
@@ -36,7 +69,7 @@ unsafe impl Sync for Processor {}
 impl Processor {
     /// 创建新处理器
     pub const fn new() -> Self {
-        Self { inner: UnsafeCell::new(PThreadManager::new()) }
+        Self { inner: UnsafeCell::new(PThreadManager::new()), switch: UnsafeCell::new(None) }
     }
 
     /// 获取内部可变引用
@@ -44,48 +77,370 @@ impl Processor {
     pub fn get_mut(&self) -> &mut ProcessorInner {
         unsafe { &mut (*self.inner.get()) }
     }
+
+    /// 把 `PThreadManager` 的当前线程设回本核（持有大内核锁的核）在 `Hart::thread` 中记录的线程
+    ///
+    /// 本核执行用户代码期间，其他核可能已经改写了共享的 `current`，每次回到内核先调用它。
+    pub fn restore_current(&self) -> Option<&mut Thread> {
+        let tid = HARTS.get_mut(smp::this_cpu()).thread?;
+        self.switch_to(tid)
+    }
+
+    /// 把 `PThreadManager` 的当前线程设为 `tid`
+    ///
+    /// `PThreadManager` 只在 `find_next` 中改变当前线程：`ThreadManager::fetch` 先返回这里登记的
+    /// `tid`（它正在运行或已经阻塞，不在就绪队列中），调度策略不会看到这次调用。
+    fn switch_to(&self, tid: ThreadId) -> Option<&mut Thread> {
+        unsafe { *self.switch.get() = Some(tid) };
+        self.get_mut().find_next()
+    }
 }
 
 /// 全局处理器实例
 pub static PROCESSOR: Processor = Processor::new();
 
+/// initproc 的 PID：它退出后内核才会关机
+pub static INITPROC: Once<ProcId> = Once::new();
+
+/// 在 wait4、waittid 中阻塞、等待其他线程退出的线程
+///
+/// 任何线程退出后全部唤醒：它们重新执行被阻塞的系统调用，各自判断等待的对象是否已退出。
+static EXIT_WAITERS: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+
+/// 阻塞线程 `tid` 直到下一次有线程退出
+///
+/// 调用者负责随后把该线程标记为阻塞态。
+pub fn wait_for_exit(tid: ThreadId) {
+    EXIT_WAITERS.lock().push(tid);
+}
+
+/// 有线程退出：唤醒所有等待者，返回唤醒的线程数
+pub fn wake_exit_waiters(processor: &mut ProcessorInner) -> usize {
+    let waiters = core::mem::take(&mut *EXIT_WAITERS.lock());
+    waiters.into_iter().filter(|&tid| wake(processor, tid)).count()
+}
+
+/// 唤醒阻塞的线程 `tid`，返回是否唤醒
+///
+/// 线程可能同时登记在多个等待队列中（如阻塞时被信号打断），也可能已经退出：
+/// 只有仍处于阻塞态的线程才重新入队，避免同一线程在就绪队列中出现两次。
+pub fn wake(processor: &mut ProcessorInner, tid: ThreadId) -> bool {
+    match processor.get_task(tid) {
+        Some(thread) if thread.last_switch == SwitchReason::Blocked && !thread.stopped => {}
+        _ => return false,
+    }
+    processor.re_enque(tid);
+    // 入队时调度器已读取阻塞原因，此后再次唤醒不应重复入队
+    processor.get_task(tid).unwrap().last_switch = SwitchReason::Voluntary;
+    true
+}
+
+/// 当前进程被停止：当前线程挂起在进程树中，进程收到 SIGCONT 或 SIGKILL 后由 `send_signal` 唤醒
+pub fn stop_current() {
+    let processor = PROCESSOR.get_mut();
+    let pid = processor.get_current_proc().unwrap().pid;
+    let thread = processor.current().unwrap();
+    thread.last_switch = SwitchReason::Blocked;
+    thread.stopped = true;
+    PROC_TREE.lock().stop(pid, thread.tid);
+    processor.make_current_blocked();
+    // 父进程可能在 wait4(WUNTRACED) 中等待
+    wake_exit_waiters(processor);
+}
+
+/// 向进程 `pid` 发送信号，进程不存在时返回 `false`
+///
+/// 被停止的进程收到 SIGCONT 或 SIGKILL 时唤醒它挂起的线程，线程回到内核时处理该信号。
+/// 信号没有被屏蔽或忽略时，还唤醒进程中阻塞的线程（见 `Thread::interruptible`）：
+/// 可重新执行的系统调用（wait4、waittid、读控制台等）在处理信号后按需重新阻塞，
+/// nanosleep 提前返回。
+pub fn send_signal(pid: ProcId, signal: SignalNo) -> bool {
+    let processor = PROCESSOR.get_mut();
+    let Some(process) = processor.get_proc(pid) else {
+        return false;
+    };
+    process.signal.add_signal(signal);
+    let (deliverable, fatal) = (deliverable(process, signal), fatal(process, signal));
+    if matches!(signal, SignalNo::SIGCONT | SignalNo::SIGKILL) {
+        let stopped = PROC_TREE.lock().resume(pid);
+        for tid in stopped {
+            if let Some(thread) = processor.get_task(tid) {
+                thread.stopped = false;
+            }
+            wake(processor, tid);
+        }
+    }
+    if deliverable {
+        let threads = processor.get_thread(pid).cloned().unwrap_or_default();
+        for tid in threads {
+            if processor.get_task(tid).is_some_and(|thread| thread.interruptible || fatal) {
+                wake(processor, tid);
+            }
+        }
+    }
+    true
+}
+
+/// 信号默认被忽略
+fn ignored_by_default(signal: SignalNo) -> bool {
+    matches!(
+        signal,
+        SignalNo::SIGCHLD | SignalNo::SIGCONT | SignalNo::SIGURG | SignalNo::SIGWINCH
+    )
+}
+
+/// 信号能否对进程 `process` 产生作用：没有被屏蔽，且有处理函数或默认动作不是忽略
+fn deliverable(process: &mut Process, signal: SignalNo) -> bool {
+    if signal == SignalNo::SIGKILL {
+        return true;
+    }
+    // `update_mask` 设置新掩码并返回旧掩码，读出后原样设回
+    let mask = process.signal.update_mask(0);
+    process.signal.update_mask(mask);
+    if mask & (1 << signal as usize) != 0 {
+        return false;
+    }
+    let handled = process.signal.get_action_ref(signal).is_some_and(|a| a.handler != 0);
+    handled || !ignored_by_default(signal)
+}
+
+/// 信号会终止进程 `process`：SIGKILL，或没有处理函数且默认动作为终止
+fn fatal(process: &mut Process, signal: SignalNo) -> bool {
+    const STOP: [SignalNo; 4] =
+        [SignalNo::SIGSTOP, SignalNo::SIGTSTP, SignalNo::SIGTTIN, SignalNo::SIGTTOU];
+    signal == SignalNo::SIGKILL
+        || (deliverable(process, signal)
+            && !process.signal.get_action_ref(signal).is_some_and(|a| a.handler != 0)
+            && !ignored_by_default(signal)
+            && !STOP.contains(&signal))
+}
+
+/// 结束当前线程；它是进程的最后一个线程时，进程以 `exit_code` 退出并在进程树中成为僵尸
+///
+/// 进程已由 `exit_process` 确定退出状态时，进程树中记录的是该状态。
+pub fn exit_current(exit_code: isize) {
+    let processor = PROCESSOR.get_mut();
+    let process = processor.get_current_proc().unwrap();
+    let (pid, status) = (process.pid, process.exit_status.unwrap_or(exit_code));
+    processor.make_current_exited(exit_code);
+    if processor.get_proc(pid).is_none() {
+        PROC_TREE.lock().exit(pid, status);
+    }
+}
+
+/// 进程被信号终止时 wait4 写回的状态中置位的标志，低位为信号编号（正常退出的退出码按原值记录，不会用到该位）
+pub const WSIGNALED: isize = 1 << 29;
+/// 进程终止时写出了核心转储：与 `WSIGNALED` 同时置位
+pub const WCOREFLAG: isize = 0x80;
+
+/// 当前进程被信号终止：`code` 是 `SignalResult::ProcessKilled` 携带的值（信号编号取负）
+///
+/// 默认动作为转储的信号先把进程写入 `core.<pid>`（见 `coredump`），此时其他线程尚未结束。
+pub fn kill_current(code: isize) {
+    let signo = code.unsigned_abs();
+    let mut status = WSIGNALED | signo as isize;
+    if coredump::dumps(signo) && coredump::dump(signo) {
+        status |= WCOREFLAG;
+    }
+    exit_process(status);
+}
+
+/// 结束当前线程所在的整个进程，进程的退出状态为 `status`（多次调用时以第一次为准）
+///
+/// 正在其他核上运行的线程回到内核后自行退出，最后一个线程退出时进程才成为僵尸。
+pub fn exit_process(status: isize) {
+    let processor = PROCESSOR.get_mut();
+    let tid = processor.current().unwrap().tid;
+    let process = processor.get_current_proc().unwrap();
+    process.exit_status.get_or_insert(status);
+    kill_siblings(tid, process.pid);
+    exit_current(status);
+}
+
+/// 结束进程 `pid` 中除 `tid`（当前线程）以外的所有线程，供 exec 和 `exit_process` 使用
+///
+/// 正在其他核上运行的线程无法立即结束：标记为 `killed` 并发送核间中断，它们回到内核后自行退出。
+/// 返回 `false` 表示仍有这样的线程，调用者应稍后重试。
+pub fn kill_siblings(tid: ThreadId, pid: ProcId) -> bool {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let siblings: Vec<ThreadId> = unsafe { (*processor).get_thread(pid).unwrap() }
+        .iter()
+        .copied()
+        .filter(|&t| t != tid)
+        .collect();
+    let mut done = true;
+    for sibling in siblings {
+        if HARTS.kick(sibling) {
+            unsafe { (*processor).get_task(sibling).unwrap().killed = true };
+            done = false;
+        } else {
+            exit_thread(sibling, 0);
+        }
+    }
+    PROCESSOR.restore_current();
+    done
+}
+
+/// 结束不在任何核上运行的线程 `tid`
+///
+/// `PThreadManager` 只能结束当前线程，这里先把它设为当前线程；
+/// 调用者负责随后用 `Processor::restore_current` 恢复本核的当前线程。
+/// 它若仍在就绪队列或某个等待队列中，留下的 TID 会在出队时被跳过。
+pub fn exit_thread(tid: ThreadId, exit_code: isize) {
+    PROCESSOR.switch_to(tid);
+    exit_current(exit_code);
+}
+
+/// 线程 `tid` 已退出：它是进程 `pid` 的分离线程时立即回收，丢弃退出码并释放它的栈
+///
+/// `PThreadManager::waittid` 以当前线程所在的进程为准，这里借用进程中任一存活线程的身份回收；
+/// 进程已没有存活线程时无需回收，线程随进程一起释放。
+pub fn reap_detached(tid: ThreadId, pid: ProcId) {
+    let processor = PROCESSOR.get_mut();
+    let Some(process) = processor.get_proc(pid) else {
+        return;
+    };
+    if !process.detached.remove(&tid) {
+        return;
+    }
+    process.stacks.free(tid, &mut process.address_space);
+    let Some(&other) = processor.get_thread(pid).and_then(|threads| threads.first()) else {
+        return;
+    };
+    PROCESSOR.switch_to(other);
+    PROCESSOR.get_mut().waittid(tid);
+}
+
+/// 调度策略：默认为 stride 调度
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+type Policy = Stride;
+/// 调度策略：多级反馈队列
+#[cfg(feature = "mlfq")]
+type Policy = crate::mlfq::Mlfq;
+/// 调度策略：先来先服务
+#[cfg(feature = "fifo")]
+type Policy = crate::fifo::Fifo;
+
 /// 线程管理器
 ///
-/// 维护所有线程实体和就绪队列。
-/// 使用 FIFO 调度策略。
+/// 线程实体和就绪队列都由调度策略 `Policy` 维护。`fetch` 先返回 `Processor::switch_to`
+/// 登记的线程，再向调度策略要下一个就绪线程，因此调度策略不必关心多核下当前线程的切换。
 pub struct ThreadManager {
-    /// 线程实体表（TID → Thread）
-    tasks: BTreeMap<ThreadId, Thread>,
-    /// 就绪队列
-    ready_queue: VecDeque<ThreadId>,
+    policy: Policy,
 }
 
 impl ThreadManager {
     /// 创建空的线程管理器
     pub fn new() -> Self {
-        Self { tasks: BTreeMap::new(), ready_queue: VecDeque::new() }
+        Self { policy: Policy::new() }
     }
 }
 
 impl Manage<Thread, ThreadId> for ThreadManager {
+    #[inline]
+    fn insert(&mut self, id: ThreadId, task: Thread) { self.policy.insert(id, task) }
+    #[inline]
+    fn get_mut(&mut self, id: ThreadId) -> Option<&mut Thread> { self.policy.get_mut(id) }
+    #[inline]
+    fn delete(&mut self, id: ThreadId) { self.policy.delete(id) }
+}
+
+impl Schedule<ThreadId> for ThreadManager {
+    #[inline]
+    fn add(&mut self, id: ThreadId) { self.policy.add(id) }
+    fn fetch(&mut self) -> Option<ThreadId> {
+        match unsafe { (*PROCESSOR.switch.get()).take() } {
+            Some(id) => Some(id),
+            None => self.policy.fetch(),
+        }
+    }
+}
+
+/// stride 调度的大步长常数
+///
+/// 优先级在 `2..=MAX_PRIORITY` 之间，步长在 `1..=BIG_STRIDE / 2` 之间，
+/// 因此任意两个就绪线程的行程差不超过 `BIG_STRIDE / 2`，用有符号差比较即可正确处理回绕。
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+const BIG_STRIDE: usize = MAX_PRIORITY;
+
+/// stride 调度策略
+///
+/// 维护所有线程实体和就绪队列。
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+pub struct Stride {
+    /// 线程实体表（TID → Thread）
+    tasks: BTreeMap<ThreadId, Box<Thread>>,
+    /// 就绪队列
+    ready_queue: VecDeque<ThreadId>,
+    /// 最近一次被调度线程的行程，新线程从这里起步，避免长期霸占 CPU
+    min_stride: usize,
+}
+
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+impl Stride {
+    /// 创建空的调度策略
+    pub fn new() -> Self {
+        Self { tasks: BTreeMap::new(), ready_queue: VecDeque::new(), min_stride: 0 }
+    }
+
+    /// 读取线程当前行程（线程不存在时视为最小）
+    #[inline]
+    fn stride_of(&self, id: ThreadId) -> usize {
+        self.tasks.get(&id).map_or(self.min_stride, |t| t.stride)
+    }
+}
+
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+impl Manage<Thread, ThreadId> for Stride {
     /// 插入线程实体
     #[inline]
-    fn insert(&mut self, id: ThreadId, task: Thread) { self.tasks.insert(id, task); }
+    fn insert(&mut self, id: ThreadId, mut task: Thread) {
+        task.stride = self.min_stride;
+        self.tasks.insert(id, Box::new(task));
+    }
     /// 获取线程可变引用
     #[inline]
-    fn get_mut(&mut self, id: ThreadId) -> Option<&mut Thread> { self.tasks.get_mut(&id) }
+    fn get_mut(&mut self, id: ThreadId) -> Option<&mut Thread> {
+        self.tasks.get_mut(&id).map(|t| &mut **t)
+    }
     /// 删除线程实体
     #[inline]
     fn delete(&mut self, id: ThreadId) { self.tasks.remove(&id); }
 }
 
-impl Schedule<ThreadId> for ThreadManager {
+#[cfg(not(any(feature = "mlfq", feature = "fifo")))]
+impl Schedule<ThreadId> for Stride {
     /// 加入就绪队列
     fn add(&mut self, id: ThreadId) { self.ready_queue.push_back(id); }
-    /// 取出下一个就绪线程
-    fn fetch(&mut self) -> Option<ThreadId> { self.ready_queue.pop_front() }
+    /// 取出行程最小的就绪线程，并令其行程前进一个步长
+    fn fetch(&mut self) -> Option<ThreadId> {
+        // 跳过已退出线程留下的 TID
+        let tasks = &self.tasks;
+        self.ready_queue.retain(|id| tasks.contains_key(id));
+        let mut best: Option<(usize, usize)> = None;
+        for (i, &id) in self.ready_queue.iter().enumerate() {
+            let stride = self.stride_of(id);
+            match best {
+                Some((_, s)) if (stride.wrapping_sub(s) as isize) >= 0 => {}
+                _ => best = Some((i, stride)),
+            }
+        }
+        let (index, stride) = best?;
+        let id = self.ready_queue.remove(index)?;
+        self.min_stride = stride;
+        if let Some(thread) = self.tasks.get_mut(&id) {
+            thread.stride = stride.wrapping_add((BIG_STRIDE / thread.priority).max(1));
+        }
+        Some(id)
+    }
 }
 
+/// 所有存活进程的 PID
+///
+/// `PThreadManager` 不提供遍历进程的接口，`ProcManager` 在增删进程时同步维护这张表，
+/// 供需要枚举进程的系统调用（如 `top` 使用的 `proc_usage`）使用。
+pub static PIDS: Mutex<BTreeSet<ProcId>> = Mutex::new(BTreeSet::new());
+
 /// 进程管理器
 ///
 /// 维护所有进程实体（PID → Process）。
@@ -103,11 +458,17 @@ impl ProcManager {
 impl Manage<Process, ProcId> for ProcManager {
     /// 插入进程实体
     #[inline]
-    fn insert(&mut self, id: ProcId, item: Process) { self.procs.insert(id, item); }
+    fn insert(&mut self, id: ProcId, item: Process) {
+        PIDS.lock().insert(id);
+        self.procs.insert(id, item);
+    }
     /// 获取进程可变引用
     #[inline]
     fn get_mut(&mut self, id: ProcId) -> Option<&mut Process> { self.procs.get_mut(&id) }
     /// 删除进程实体
     #[inline]
-    fn delete(&mut self, id: ProcId) { self.procs.remove(&id); }
+    fn delete(&mut self, id: ProcId) {
+        PIDS.lock().remove(&id);
+        self.procs.remove(&id);
+    }
 }
@@ -0,0 +1,143 @@
+//! 进程资源限制
+//!
+//! 每个进程在 `Process::rlimits` 中保存一组限制，fork / spawn 时由子进程继承，exec 时保持不变。
+//! 超过限制的请求返回错误，而不是耗尽内核堆后 panic：
+//!
+//! - `RLIMIT_AS`：进程映射的用户内存（字节），在 exec、thread_create 等建立映射处检查；
+//! - `RLIMIT_NOFILE`：打开的文件描述符数，在 open、pipe 处检查；
+//! - `RLIMIT_NPROC`：调用者所在会话中未退出的进程数（本内核没有用户，以会话代替 Linux 的同一用户），
+//!   在 fork、spawn 处检查，因此孙进程也计入、无法通过层层 fork 绕过；
+//! - `RLIMIT_NTHREAD`（本内核扩展）：进程的线程数，在 thread_create 处检查；
+//! - `RLIMIT_CPU`：CPU 时间（秒），超过软限制后每多用 1 秒收到一次 SIGXCPU，超过硬限制收到 SIGKILL；
+//! - `RLIMIT_CORE`：核心转储文件的大小（字节），超过时不转储；
+//! - `RLIMIT_STACK`：主线程栈的大小（字节），栈按需增长到这里为止，再往下访问收到 SIGSEGV。
+//!   硬限制是为栈预留的区域大小，不能提高。
+//!
+//! 编号与 Linux 相同，其余 Linux 定义的资源可以读写，但不做检查。
+//! 软限制可以在硬限制以内任意调整，硬限制只能降低。
+
+use crate::{
+    process::{DEFAULT_STACK_SIZE, USER_STACK_PAGES},
+    processor,
+    timer::CLOCK_FREQ,
+    Sv39, PROCESSOR,
+};
+use tg_kernel_vm::page_table::MmuMeta;
+use tg_signal::SignalNo;
+use tg_task_manage::ProcId;
+
+/// CPU 时间（秒）
+pub const RLIMIT_CPU: usize = 0;
+/// 主线程栈的大小（字节）
+pub const RLIMIT_STACK: usize = 3;
+/// 核心转储文件的大小（字节）
+pub const RLIMIT_CORE: usize = 4;
+/// 子进程数
+pub const RLIMIT_NPROC: usize = 6;
+/// 打开的文件描述符数
+pub const RLIMIT_NOFILE: usize = 7;
+/// 映射的用户内存（字节）
+pub const RLIMIT_AS: usize = 9;
+/// 线程数（本内核扩展，Linux 没有对应的资源）
+pub const RLIMIT_NTHREAD: usize = 16;
+/// 资源种类数
+const RLIM_NLIMITS: usize = 17;
+/// 不限制
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+/// 一项资源的限制，与用户态 `struct rlimit` 布局相同
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RLimit {
+    /// 软限制：实际生效的限制
+    pub cur: usize,
+    /// 硬限制：软限制的上限
+    pub max: usize,
+}
+
+impl RLimit {
+    const INFINITY: Self = Self::new(RLIM_INFINITY);
+
+    const fn new(limit: usize) -> Self {
+        Self { cur: limit, max: limit }
+    }
+}
+
+/// 进程的全部资源限制
+pub struct ResourceLimits {
+    limits: [RLimit; RLIM_NLIMITS],
+    /// 下一次发送 SIGXCPU 的 CPU 时间（秒）
+    next_xcpu: usize,
+}
+
+impl ResourceLimits {
+    /// initproc 的默认限制
+    pub fn new() -> Self {
+        let mut limits = [RLimit::INFINITY; RLIM_NLIMITS];
+        limits[RLIMIT_AS] = RLimit::new(64 << 20);
+        limits[RLIMIT_NOFILE] = RLimit::new(256);
+        limits[RLIMIT_NPROC] = RLimit::new(256);
+        limits[RLIMIT_NTHREAD] = RLimit::new(256);
+        limits[RLIMIT_STACK] = RLimit {
+            cur: DEFAULT_STACK_SIZE,
+            max: USER_STACK_PAGES << Sv39::PAGE_BITS,
+        };
+        Self { limits, next_xcpu: 0 }
+    }
+
+    /// 子进程继承的限制（子进程的 CPU 时间从 0 开始计算）
+    pub fn inherit(&self) -> Self {
+        Self { limits: self.limits, next_xcpu: 0 }
+    }
+
+    /// 资源 `resource` 的限制，编号无效时返回 `None`
+    pub fn get(&self, resource: usize) -> Option<RLimit> {
+        self.limits.get(resource).copied()
+    }
+
+    /// 设置资源 `resource` 的限制，编号无效、软限制超过硬限制或试图提高硬限制时返回 `false`
+    pub fn set(&mut self, resource: usize, limit: RLimit) -> bool {
+        match self.limits.get_mut(resource) {
+            Some(old) if limit.cur <= limit.max && limit.max <= old.max => {
+                *old = limit;
+                if resource == RLIMIT_CPU {
+                    self.next_xcpu = 0;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 已用 `used` 个单位时，能否再使用 `more` 个
+    #[inline]
+    pub fn allows(&self, resource: usize, used: usize, more: usize) -> bool {
+        used.saturating_add(more) <= self.limits[resource].cur
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 检查进程 `pid` 的 CPU 时间是否超过 `RLIMIT_CPU`，超过时向它发送信号
+///
+/// 由调度循环在每次 Trap 记账之后调用。
+pub fn check_cpu(pid: ProcId) {
+    let Some(process) = PROCESSOR.get_mut().get_proc(pid) else {
+        return;
+    };
+    let limit = process.rlimits.limits[RLIMIT_CPU];
+    if limit.cur == RLIM_INFINITY {
+        return;
+    }
+    let seconds = ((process.usage.utime + process.usage.stime) / CLOCK_FREQ) as usize;
+    if seconds >= limit.max {
+        processor::send_signal(pid, SignalNo::SIGKILL);
+    } else if seconds >= limit.cur.max(process.rlimits.next_xcpu) {
+        process.rlimits.next_xcpu = seconds + 1;
+        processor::send_signal(pid, SignalNo::SIGXCPU);
+    }
+}
@@ -0,0 +1,285 @@
+//! 多核（SMP）支持
+//!
+//! ## 设计
+//!
+//! - **启动**：引导核完成全部初始化后，通过 SBI HSM 扩展（`hart_start`）唤醒其余核，
+//!   从核进入 `_secondary_start`，切换到内核地址空间后进入同一个调度循环；
+//! - **大内核锁**：`KERNEL_LOCK` 保护所有内核态数据（`PROCESSOR`、文件系统、设备）。
+//!   每个核只在执行用户代码期间释放它，因此系统调用处理代码无需改动；
+//! - **每核状态**：`PThreadManager` 只记录一个 `current`，多核下由 `Hart::thread`
+//!   记录本核的当前线程，每次回到内核时用 `Processor::restore_current` 设回；
+//!   `lock` 在获取大内核锁时记下持有者，内核代码由 `this_cpu` 得知自己在哪个核上；
+//! - **传送门**：每个核使用 `MultislotPortal` 中以逻辑核号为下标的独立 slot；
+//! - **唤醒**：就绪线程可能由任意核加入共享就绪队列，此后通过 SBI IPI 扩展向空闲核
+//!   发送核间中断，把它们从 `wfi` 中唤醒。
+//! - **TLB**：回到用户态时传送门切换 `satp` 并刷新本核 TLB；改严另一个核上正在使用的
+//!   页表项时（如写时复制的 fork 去掉 W 位），通过 SBI RFENCE 扩展让那些核刷新。
+//!
+//! 逻辑核号 `cpu` 是 `HARTS` 的下标：引导核为 0，其余核按启动顺序编号；
+//! 物理 hart ID 只用于 SBI 调用。
+//!
+//! 内核运行在 OpenSBI 之上（`-bios default`），它只放行一个核进入 `_start`，其余核停在 HSM 的停止状态等待
+//! `hart_start`。没有 HSM 的固件会同时放行所有核，此时只有第一个进入 `_start` 的核继续引导，
+//! 其余核永久停在 `wfi` 中，内核以单核运行。
+
+#[cfg(target_arch = "riscv64")]
+use core::arch::asm;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering},
+};
+use spin::{Mutex, MutexGuard};
+use tg_console::log;
+use tg_kernel_context::foreign::MultislotPortal;
+use tg_task_manage::ThreadId;
+
+/// 支持的最大核数
+pub const MAX_HARTS: usize = 4;
+/// 每个核的内核栈大小（32 页 = 128 KiB）
+pub const STACK_SIZE: usize = 32 * 4096;
+
+/// 所有核的内核栈（下标为逻辑核号）
+#[unsafe(link_section = ".boot.stack")]
+pub static mut STACKS: [[u8; STACK_SIZE]; MAX_HARTS] = [[0u8; STACK_SIZE]; MAX_HARTS];
+
+/// 是否已有核在 `_start` 中成为引导核
+///
+/// 放在 `.data` 段：引导核清零 BSS 时不会把它重置，迟到的核仍然会看到 1 而停住。
+#[unsafe(link_section = ".data")]
+pub static BOOT_CLAIMED: AtomicU32 = AtomicU32::new(0);
+
+/// 大内核锁：持有期间可以访问任意内核数据
+pub static KERNEL_LOCK: Mutex<()> = Mutex::new(());
+
+/// 持有大内核锁的逻辑核号
+static HOLDER: AtomicUsize = AtomicUsize::new(0);
+
+/// 逻辑核 `cpu` 获取大内核锁
+pub fn lock(cpu: usize) -> MutexGuard<'static, ()> {
+    let guard = KERNEL_LOCK.lock();
+    HOLDER.store(cpu, Ordering::Relaxed);
+    guard
+}
+
+/// 本核的逻辑核号（只能在持有大内核锁时调用）
+#[inline]
+pub fn this_cpu() -> usize {
+    HOLDER.load(Ordering::Relaxed)
+}
+
+/// 多核共享的异界传送门（每个核使用自己的 slot）
+static PORTAL: AtomicPtr<MultislotPortal> = AtomicPtr::new(core::ptr::null_mut());
+
+/// 单个核的状态
+#[derive(Clone, Copy)]
+pub struct Hart {
+    /// 物理 hart ID
+    pub hart_id: usize,
+    /// 是否已上线
+    pub online: bool,
+    /// 是否在 `wfi` 中等待
+    pub idle: bool,
+    /// 本核正在执行用户代码的线程
+    pub current: Option<ThreadId>,
+    /// 本核的当前线程：从调度循环取出线程到处理完它的 Trap 为止
+    pub thread: Option<ThreadId>,
+}
+
+impl Hart {
+    const OFFLINE: Self =
+        Self { hart_id: usize::MAX, online: false, idle: false, current: None, thread: None };
+}
+
+/// 所有核的状态表（只能在持有 `KERNEL_LOCK` 时访问）
+pub struct Harts {
+    inner: UnsafeCell<[Hart; MAX_HARTS]>,
+}
+
+unsafe impl Sync for Harts {}
+
+impl Harts {
+    const fn new() -> Self {
+        Self { inner: UnsafeCell::new([Hart::OFFLINE; MAX_HARTS]) }
+    }
+
+    /// 获取逻辑核 `cpu` 的状态
+    #[inline]
+    pub fn get_mut(&self, cpu: usize) -> &mut Hart {
+        unsafe { &mut (*self.inner.get())[cpu] }
+    }
+
+    /// 是否有核正在运行用户线程
+    pub fn any_running(&self) -> bool {
+        unsafe { (*self.inner.get()).iter().any(|h| h.current.is_some()) }
+    }
+
+    /// 若线程 `tid` 正在某个核上运行，向该核发送核间中断使其尽快回到内核，返回是否在运行
+    pub fn kick(&self, tid: ThreadId) -> bool {
+        match unsafe { (*self.inner.get()).iter() }.find(|h| h.current == Some(tid)) {
+            Some(hart) => {
+                sbi_send_ipi(1 << hart.hart_id, 0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 让正在运行 `tids` 中任一线程的核刷新 TLB（这些线程的页表项被改为更严格的权限后调用）
+    pub fn flush_tlb(&self, tids: &[ThreadId]) {
+        let mask = unsafe { (*self.inner.get()).iter() }
+            .filter(|h| h.current.is_some_and(|tid| tids.contains(&tid)))
+            .fold(0usize, |mask, h| mask | (1 << h.hart_id));
+        if mask != 0 {
+            sbi_remote_sfence_vma(mask, 0);
+        }
+    }
+
+    /// 向所有空闲核发送核间中断，让它们重新检查就绪队列
+    pub fn kick_idle(&self) {
+        let mask = unsafe { (*self.inner.get()).iter() }
+            .filter(|h| h.online && h.idle)
+            .fold(0usize, |mask, h| mask | (1 << h.hart_id));
+        if mask != 0 {
+            sbi_send_ipi(mask, 0);
+        }
+    }
+}
+
+/// 全局核状态表
+pub static HARTS: Harts = Harts::new();
+
+/// 记录传送门地址（引导核初始化传送门后调用）
+pub fn set_portal(portal: &'static mut MultislotPortal) {
+    PORTAL.store(portal, Ordering::Release);
+}
+
+/// 获取传送门
+#[inline]
+pub fn portal() -> &'static mut MultislotPortal {
+    unsafe { &mut *PORTAL.load(Ordering::Acquire) }
+}
+
+/// 开启本核需要的中断源：时钟中断（抢占）和软件中断（IPI）
+pub fn enable_interrupts() {
+    unsafe {
+        riscv::register::sie::set_stimer();
+        riscv::register::sie::set_ssoft();
+    }
+}
+
+/// 清除本核挂起的软件中断
+#[inline]
+pub fn clear_ipi() {
+    #[cfg(target_arch = "riscv64")]
+    unsafe { asm!("csrc sip, {}", in(reg) 1usize << 1) };
+}
+
+/// 在 `wfi` 中等待中断（时钟、IPI 或外部中断）
+#[inline]
+pub fn wait_for_interrupt() {
+    #[cfg(target_arch = "riscv64")]
+    unsafe { asm!("wfi") };
+    clear_ipi();
+}
+
+/// 启动其余核
+///
+/// `boot_hart` 为引导核的物理 hart ID，`entry` 为从核入口的物理地址。
+pub fn start_secondary_harts(boot_hart: usize, entry: usize) {
+    // 持锁期间从核只能在调度循环入口等待，不会看到未登记完的状态表
+    let _guard = lock(0);
+    let boot = HARTS.get_mut(0);
+    boot.hart_id = boot_hart;
+    boot.online = true;
+    // 没有 HSM 扩展的固件可能不能正确处理未知的调用，先探测
+    if !sbi_probe_extension(EID_HSM) {
+        log::warn!("SBI has no HSM extension, running on a single hart");
+        return;
+    }
+    let mut cpu = 1;
+    for hart_id in 0..MAX_HARTS {
+        if hart_id == boot_hart || cpu >= MAX_HARTS {
+            continue;
+        }
+        // 先登记再启动：从核进入调度循环时需要看到自己的状态
+        let hart = HARTS.get_mut(cpu);
+        hart.hart_id = hart_id;
+        hart.online = true;
+        match sbi_hart_start(hart_id, entry, cpu) {
+            Ok(()) => {
+                log::info!("hart {hart_id} started as cpu {cpu}");
+                cpu += 1;
+            }
+            Err(err) => {
+                log::warn!("failed to start hart {hart_id}: sbi error {err}");
+                *HARTS.get_mut(cpu) = Hart::OFFLINE;
+            }
+        }
+    }
+    log::info!("{cpu} hart(s) online");
+}
+
+/// SBI 基础扩展 ID
+const EID_BASE: usize = 0x10;
+/// SBI HSM 扩展 ID（"HSM"）
+const EID_HSM: usize = 0x48534D;
+/// SBI IPI 扩展 ID（"sPI"）
+const EID_IPI: usize = 0x735049;
+/// SBI RFENCE 扩展 ID（"RFNC"）
+const EID_RFENCE: usize = 0x52464E43;
+
+/// SBI 调用：返回 (error, value)
+#[inline]
+fn sbi_call(
+    eid: usize,
+    fid: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+) -> (isize, usize) {
+    let (error, value);
+    #[cfg(target_arch = "riscv64")]
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a6") fid,
+            in("a7") eid,
+        );
+    }
+    #[cfg(not(target_arch = "riscv64"))]
+    {
+        let _ = (eid, fid, arg0, arg1, arg2, arg3);
+        (error, value) = (-2, 0);
+    }
+    (error, value)
+}
+
+/// sbi_probe_extension：固件是否实现了扩展 `eid`
+fn sbi_probe_extension(eid: usize) -> bool {
+    matches!(sbi_call(EID_BASE, 3, eid, 0, 0, 0), (0, value) if value != 0)
+}
+
+/// sbi_hart_start：从 `start_addr` 以 S 态启动 `hart_id`，a1 为 `opaque`
+fn sbi_hart_start(hart_id: usize, start_addr: usize, opaque: usize) -> Result<(), isize> {
+    match sbi_call(EID_HSM, 0, hart_id, start_addr, opaque, 0) {
+        (0, _) => Ok(()),
+        (err, _) => Err(err),
+    }
+}
+
+/// sbi_send_ipi：向 `hart_mask`（以 `hart_mask_base` 为基准）中的核发送软件中断
+fn sbi_send_ipi(hart_mask: usize, hart_mask_base: usize) {
+    sbi_call(EID_IPI, 0, hart_mask, hart_mask_base, 0, 0);
+}
+
+/// sbi_remote_sfence_vma：让 `hart_mask`（以 `hart_mask_base` 为基准）中的核刷新整个 TLB
+///
+/// `start_addr` 与 `size` 都为 0 表示全部地址；SBI 实现在远程核完成刷新后才返回。
+fn sbi_remote_sfence_vma(hart_mask: usize, hart_mask_base: usize) {
+    sbi_call(EID_RFENCE, 1, hart_mask, hart_mask_base, 0, 0);
+}
@@ -0,0 +1,860 @@
+//! 扩展系统调用
+//!
+//! 主循环先把系统调用交给本模块的 `handle`，返回 `SyscallResult::Unsupported`
+//! 时再交给 `tg-syscall` 分发。本模块处理三类调用：
+//!
+//! - `tg-syscall` 没有定义 trait 的系统调用（如 nanosleep、getrusage）；
+//! - 参数超出 `tg-syscall` trait 签名的系统调用（如 execve 的 argv、envp）；
+//! - 需要阻塞后重新执行的系统调用（如 wait4、waittid、读标准输入），见 `Restart`。
+//!
+//! 调用号沿用 Linux RISC-V 的编号；Linux 没有的系统调用从 2000 开始编号。
+//! 出错时返回取负的 Linux 错误码（下面的 `E*` 常量），不再像 `tg-syscall` 的实现那样一律返回 -1。
+//! 例外是前几章已有的 wait4（没有子进程）和 sbrk：沿用的测例检查返回值为 -1，仍返回 `LEGACY_ERR`。
+
+use crate::{
+    fs::{read_all, Fd, FS},
+    mmap::{
+        FileMapping, MemoryMap, MAP_ANONYMOUS, MAP_FIXED, MAP_FIXED_NOREPLACE, MAP_PRIVATE,
+        MAP_SHARED, PROT_EXEC, PROT_READ, PROT_WRITE,
+    },
+    proc_tree::{Reap, PROC_TREE},
+    process::{CpuUsage, Process, Thread, ARG_MAX},
+    processor::{self, ProcessorInner, PIDS},
+    rlimit::{RLimit, RLIMIT_AS, RLIMIT_NPROC, RLIMIT_NTHREAD},
+    smp, timer, tty, Sv39, PROCESSOR,
+};
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+use spin::Mutex;
+use tg_console::log;
+use tg_easy_fs::{FSManager, OpenFlags};
+use tg_kernel_context::LocalContext;
+use tg_kernel_vm::page_table::MmuMeta;
+use tg_signal::SignalNo;
+use tg_syscall::{SyscallId, SyscallResult, TimeSpec};
+use tg_task_manage::{ProcId, ThreadId};
+use xmas_elf::ElfFile;
+
+/// execve(path, argv, envp)
+pub const EXECVE: SyscallId = SyscallId(221);
+/// nanosleep(req, rem)
+pub const NANOSLEEP: SyscallId = SyscallId(101);
+/// spawn(path, argv, fds, nfds)：沿用 tg-syscall 的调用号
+pub const SPAWN: SyscallId = SyscallId(400);
+/// wait4(pid, status, options)
+pub const WAIT4: SyscallId = SyscallId(260);
+/// read(fd, buf, count)：只接管标准输入，其余交给 `tg-syscall`
+pub const READ: SyscallId = SyscallId(63);
+/// getppid()
+pub const GETPPID: SyscallId = SyscallId(173);
+/// setpgid(pid, pgid)
+pub const SETPGID: SyscallId = SyscallId(154);
+/// getpgid(pid)
+pub const GETPGID: SyscallId = SyscallId(155);
+/// getsid(pid)
+pub const GETSID: SyscallId = SyscallId(156);
+/// setsid()
+pub const SETSID: SyscallId = SyscallId(157);
+/// getrlimit(resource, rlim)
+pub const GETRLIMIT: SyscallId = SyscallId(163);
+/// setrlimit(resource, rlim)
+pub const SETRLIMIT: SyscallId = SyscallId(164);
+/// getrusage(who, usage)
+pub const GETRUSAGE: SyscallId = SyscallId(165);
+/// getcpu(cpu, node)
+pub const GETCPU: SyscallId = SyscallId(168);
+/// proc_usage(pid, usage)：查询 PID 不小于 `pid` 的第一个进程，返回其 PID
+pub const PROC_USAGE: SyscallId = SyscallId(2000);
+/// tcgetpgrp()：控制台的前台进程组
+pub const TCGETPGRP: SyscallId = SyscallId(2001);
+/// tcsetpgrp(pgid)：设置控制台的前台进程组
+pub const TCSETPGRP: SyscallId = SyscallId(2002);
+/// thread_create_with_stack(entry, arg, stack_size)：指定栈大小创建线程
+pub const THREAD_CREATE_WITH_STACK: SyscallId = SyscallId(2003);
+/// thread_detach(tid)：分离线程，退出后自动回收
+pub const THREAD_DETACH: SyscallId = SyscallId(2004);
+/// waittid(tid)：沿用 tg-syscall 的调用号，改为阻塞等待
+pub const WAITTID: SyscallId = SyscallId::WAITTID;
+/// mmap(addr, len, prot, flags, fd, offset)
+pub const MMAP: SyscallId = SyscallId(222);
+/// munmap(addr, len)
+pub const MUNMAP: SyscallId = SyscallId(215);
+/// mprotect(addr, len, prot)
+pub const MPROTECT: SyscallId = SyscallId(226);
+/// msync(addr, len, flags)
+pub const MSYNC: SyscallId = SyscallId(227);
+/// sbrk(size)：与 mmap 系列一起在本模块处理，本章不初始化 tg-syscall 的 `Memory`
+pub const SBRK: SyscallId = SyscallId(214);
+
+/// 错误：不允许的操作，如 setsid 的调用者已是组长、setrlimit 提高硬限制
+const EPERM: isize = -1;
+/// 错误：execve / spawn 的程序不存在
+const ENOENT: isize = -2;
+/// 错误：没有这个进程或线程
+const ESRCH: isize = -3;
+/// 错误：nanosleep 被信号打断
+const EINTR: isize = -4;
+/// 错误：execve / spawn 的参数超过 `ARG_MAX`
+const E2BIG: isize = -7;
+/// 错误：execve / spawn 的程序不是可加载的 ELF
+const ENOEXEC: isize = -8;
+/// 错误：`fd` 不是打开的文件（mmap 还要求是普通文件）
+const EBADF: isize = -9;
+/// 错误：超过 `RLIMIT_NPROC`、`RLIMIT_NTHREAD`
+const EAGAIN: isize = -11;
+/// 错误：超过 `RLIMIT_AS`、内存不足，或 mprotect / msync 的区间不在 mmap 区域内
+const ENOMEM: isize = -12;
+/// 错误：文件的打开方式不允许所要求的映射
+const EACCES: isize = -13;
+/// 错误：用户地址不可访问
+const EFAULT: isize = -14;
+/// 错误：`MAP_FIXED_NOREPLACE` 的区间已被占用
+const EEXIST: isize = -17;
+/// 错误：参数无效，如 waittid 的线程已分离
+const EINVAL: isize = -22;
+/// 错误：调用者不在控制台的会话中
+const ENOTTY: isize = -25;
+/// 错误：waittid 等待自己
+const EDEADLK: isize = -35;
+/// 前几章的 wait、sbrk 出错时的返回值，沿用的测例检查这个值
+const LEGACY_ERR: isize = -1;
+
+/// getrusage 的 `who`：当前进程
+const RUSAGE_SELF: isize = 0;
+/// getrusage 的 `who`：当前线程
+const RUSAGE_THREAD: isize = 1;
+
+/// wait4 的 `options`：子进程尚未退出时立即返回 0
+const WNOHANG: usize = 1;
+/// wait4 的 `options`：同时报告已停止的子进程
+const WUNTRACED: usize = 2;
+/// wait4 报告子进程停止时写入的状态：`WSTOPPED | 信号编号`（退出码按原值写入，不会用到该位）
+const WSTOPPED: i32 = 1 << 30;
+
+/// 系统调用需要稍后重新执行时的等待方式
+///
+/// 主循环回退 pc（a0 保持原参数不变），线程再次运行时重新陷入执行同一个系统调用；
+/// 期间收到的信号照常处理，信号处理函数返回后同样会重新执行。
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// 阻塞：线程已登记到某个等待队列，条件满足时被唤醒
+    Block,
+    /// 让出 CPU，下次被调度时重试
+    Yield,
+}
+
+/// 本次系统调用要求重新执行（只在持有大内核锁时访问，由主循环在分发后立即取走）
+static RESTART: Mutex<Option<Restart>> = Mutex::new(None);
+
+/// 取出本次系统调用的重新执行要求
+pub fn take_restart() -> Option<Restart> {
+    RESTART.lock().take()
+}
+
+/// 要求重新执行本次系统调用（返回值不会写回用户态）
+fn restart(how: Restart) -> isize {
+    *RESTART.lock() = Some(how);
+    0
+}
+
+/// spawn 的 fd 映射中表示"不继承"的值
+const NO_FD: usize = usize::MAX;
+/// spawn 的 fd 映射最多包含的项数
+const MAX_SPAWN_FDS: usize = 64;
+
+/// getrusage / proc_usage 写回用户态的资源使用统计
+#[repr(C)]
+pub struct RUsage {
+    /// 用户态时间
+    pub utime: TimeSpec,
+    /// 内核态时间
+    pub stime: TimeSpec,
+    /// 被调度上 CPU 的次数
+    pub nswitch: usize,
+    /// 主动让出 CPU 的次数
+    pub nvcsw: usize,
+    /// 被动让出 CPU 的次数
+    pub nivcsw: usize,
+    /// 线程数
+    pub nthreads: usize,
+}
+
+impl RUsage {
+    fn new(usage: &CpuUsage, nthreads: usize) -> Self {
+        Self {
+            utime: timer::ticks_to_timespec(usage.utime),
+            stime: timer::ticks_to_timespec(usage.stime),
+            nswitch: usage.nswitch as _,
+            nvcsw: usage.nvcsw as _,
+            nivcsw: usage.nivcsw as _,
+            nthreads,
+        }
+    }
+}
+
+/// 处理 `tg-syscall` 不支持的系统调用
+pub fn handle(id: SyscallId, args: [usize; 6]) -> SyscallResult {
+    let ret = match id {
+        EXECVE => execve(args[0], args[1], args[2]),
+        SPAWN => spawn(args[0], args[1], args[2], args[3]),
+        READ if reads_console(args[0]) => read_stdin(args[1], args[2]),
+        WAIT4 => wait4(args[0] as isize, args[1], args[2]),
+        GETPPID => getppid(),
+        SETPGID => setpgid(args[0], args[1]),
+        GETPGID => getpgid(args[0]),
+        GETSID => getsid(args[0]),
+        SETSID => setsid(),
+        TCGETPGRP => tcgetpgrp(),
+        TCSETPGRP => tcsetpgrp(args[0]),
+        THREAD_CREATE_WITH_STACK => thread_create(args[0], args[1], args[2]),
+        THREAD_DETACH => thread_detach(args[0]),
+        WAITTID => waittid(args[0]),
+        NANOSLEEP => nanosleep(args[0], args[1]),
+        GETRLIMIT => getrlimit(args[0], args[1]),
+        SETRLIMIT => setrlimit(args[0], args[1]),
+        GETRUSAGE => getrusage(args[0] as isize, args[1]),
+        GETCPU => getcpu(args[0], args[1]),
+        PROC_USAGE => proc_usage(args[0], args[1]),
+        MMAP => mmap(args[0], args[1], args[2], args[3], args[4] as isize, args[5]),
+        MUNMAP => munmap(args[0], args[1]),
+        MPROTECT => mprotect(args[0], args[1], args[2]),
+        MSYNC => msync(args[0], args[1], args[2]),
+        SBRK => sbrk(args[0] as isize),
+        _ => return SyscallResult::Unsupported(id),
+    };
+    SyscallResult::Done(ret)
+}
+
+/// execve：加载 `path` 指定的程序替换当前进程，`argv`、`envp` 为以 NULL 结尾的字符串指针数组
+///
+/// 成功时返回 argc：主循环把返回值写入新上下文的 a0，与用户态入口约定的参数一致。
+/// 新程序加载成功后才结束本进程的其他线程；其中有线程正在其他核上运行时让出 CPU 稍后重试。
+fn execve(path: usize, argv: usize, envp: usize) -> isize {
+    let processor = PROCESSOR.get_mut();
+    let tid = processor.current().unwrap().tid;
+    let current = processor.get_current_proc().unwrap();
+    let mut budget = ARG_MAX;
+    let name = match read_cstr(current, path, &mut budget) {
+        Ok(name) => name,
+        Err(err) => return err,
+    };
+    let (argv, envp) = match (
+        read_cstr_array(current, argv, &mut budget),
+        read_cstr_array(current, envp, &mut budget),
+    ) {
+        (Ok(argv), Ok(envp)) => (argv, envp),
+        (Err(err), _) | (_, Err(err)) => {
+            log::error!("execve: argv/envp unreadable or larger than {ARG_MAX} bytes");
+            return err;
+        }
+    };
+    let Some(data) = read_program(&name) else {
+        return ENOENT;
+    };
+    let Some((image, thread)) = ElfFile::new(&data)
+        .ok()
+        .and_then(|elf| Process::from_elf(elf, &argv, &envp))
+    else {
+        return ENOEXEC;
+    };
+    if !current.rlimits.allows(RLIMIT_AS, 0, image.mapped_pages() << Sv39::PAGE_BITS) {
+        log::error!("execve: {name} exceeds RLIMIT_AS");
+        return ENOMEM;
+    }
+    if !processor::kill_siblings(tid, current.pid) {
+        return restart(Restart::Yield);
+    }
+    current.exec(image, thread);
+    argv.len() as isize
+}
+
+/// spawn：直接从 ELF 创建子进程并返回其 PID，不复制父进程地址空间
+///
+/// `fds` 指向 `nfds` 个父进程 fd：子进程的 fd `i` 是父进程 fd `fds[i]` 的副本，
+/// `NO_FD` 表示该位置留空。`fds` 为 0 时子进程只继承 0、1、2。
+fn spawn(path: usize, argv: usize, fds: usize, nfds: usize) -> isize {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let current = unsafe { (*processor).get_current_proc().unwrap() };
+    let mut budget = ARG_MAX;
+    let (name, argv) = match (
+        read_cstr(current, path, &mut budget),
+        read_cstr_array(current, argv, &mut budget),
+    ) {
+        (Ok(name), Ok(argv)) => (name, argv),
+        (Err(err), _) | (_, Err(err)) => return err,
+    };
+    if !current.rlimits.allows(RLIMIT_NPROC, PROC_TREE.lock().session_size(current.pid), 1) {
+        return EAGAIN;
+    }
+    let fds = if fds == 0 {
+        vec![0, 1, 2]
+    } else if nfds > MAX_SPAWN_FDS {
+        return EINVAL;
+    } else {
+        let Some(fds) = (0..nfds)
+            .map(|i| current.translate::<usize>(fds + i * core::mem::size_of::<usize>()))
+            .map(|ptr| ptr.map(|ptr| unsafe { *ptr.as_ptr() }))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            return EFAULT;
+        };
+        fds
+    };
+    // 子进程的 fd 表：先检查所有 fd 都有效，再逐个复制
+    let mut fd_table = Vec::with_capacity(fds.len());
+    for fd in fds {
+        if fd == NO_FD {
+            fd_table.push(None);
+            continue;
+        }
+        match current.fd_table.get(fd) {
+            Some(Some(file)) => fd_table.push(Some(Mutex::new(file.lock().clone()))),
+            _ => return EBADF,
+        }
+    }
+    let Some(data) = read_program(&name) else {
+        return ENOENT;
+    };
+    let Some((mut process, thread)) = ElfFile::new(&data)
+        .ok()
+        .and_then(|elf| Process::from_elf(elf, &argv, &[]))
+    else {
+        return ENOEXEC;
+    };
+    process.rlimits = current.rlimits.inherit();
+    if !process.rlimits.allows(RLIMIT_AS, 0, process.mapped_pages() << Sv39::PAGE_BITS) {
+        log::error!("spawn: {name} exceeds RLIMIT_AS");
+        return ENOMEM;
+    }
+    process.fd_table = fd_table;
+    let (parent, pid, tid) = (current.pid, process.pid, thread.tid);
+    PROC_TREE.lock().insert(pid, parent);
+    unsafe {
+        (*processor).add_proc(pid, process, parent);
+        (*processor).add(tid, thread, pid);
+    }
+    pid.get_usize() as isize
+}
+
+/// thread_create：在当前进程中创建新线程，入口为 `entry`，参数为 `arg`，返回 TID
+///
+/// 线程栈由 `Process::stacks` 分配，`stack_size` 向上取整到页，下方留有保护页；
+/// 程序有 `PT_TLS` 段时，栈顶额外放置线程的 TLS 块并设置 `tp`。
+/// 栈和 TLS 块在线程被 `waittid` 回收时释放。超过 `RLIMIT_NTHREAD` 时返回 `EAGAIN`，
+/// 超过 `RLIMIT_AS` 或栈区域用尽时返回 `ENOMEM`。
+pub fn thread_create(entry: usize, arg: usize, stack_size: usize) -> isize {
+    const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let current = unsafe { (*processor).get_current_proc().unwrap() };
+    let tls_size = current.tls.as_ref().map_or(0, |tls| tls.footprint());
+    let pages = (stack_size.div_ceil(PAGE_SIZE) + tls_size.div_ceil(PAGE_SIZE)).max(1);
+    let nthreads = unsafe { (*processor).get_thread(current.pid) }.map_or(0, |t| t.len());
+    if !current.rlimits.allows(RLIMIT_NTHREAD, nthreads, 1) {
+        return EAGAIN;
+    }
+    if !current.can_map(pages) {
+        return ENOMEM;
+    }
+    let satp = (8 << 60) | current.address_space.root_ppn().val();
+    let mut context = LocalContext::user(entry);
+    *context.a_mut(0) = arg;
+    let mut thread = Thread::new(satp, context);
+    let Some(sp) = current.stacks.alloc(thread.tid, pages, &mut current.address_space) else {
+        return ENOMEM;
+    };
+    let context = &mut thread.context.context;
+    match &current.tls {
+        Some(tls) => {
+            let tp = tls.place(&mut current.address_space, sp);
+            *context.x_mut(4) = tp; // x4 即 tp
+            *context.sp_mut() = tp & !0xf;
+        }
+        None => *context.sp_mut() = sp,
+    }
+    // 新线程继承创建者的调度优先级
+    thread.priority = unsafe { (*processor).current().unwrap().priority };
+    let tid = thread.tid;
+    current.joinable.insert(tid);
+    unsafe { (*processor).add(tid, thread, current.pid) };
+    tid.get_usize() as isize
+}
+
+/// waittid：等待本进程的线程 `tid` 退出并回收它，返回其退出码
+///
+/// 线程尚未退出时阻塞，任一线程退出后重新检查。回收时释放线程的栈和 TLS 块。
+/// `tid` 不是本进程的线程或已被回收时返回 `ESRCH`，已分离时返回 `EINVAL`，等待自己时返回 `EDEADLK`。
+fn waittid(tid: usize) -> isize {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let current = unsafe { (*processor).current().unwrap().tid };
+    let process = unsafe { (*processor).get_current_proc().unwrap() };
+    let tid = ThreadId::from_usize(tid);
+    if tid == current {
+        return EDEADLK;
+    }
+    if process.detached.contains(&tid) {
+        return EINVAL;
+    }
+    if !process.joinable.contains(&tid) {
+        return ESRCH;
+    }
+    match unsafe { (*processor).waittid(tid) } {
+        Some(exit_code) => {
+            process.joinable.remove(&tid);
+            process.stacks.free(tid, &mut process.address_space);
+            exit_code
+        }
+        None => {
+            processor::wait_for_exit(current);
+            restart(Restart::Block)
+        }
+    }
+}
+
+/// thread_detach：分离本进程的线程 `tid`（0 表示当前线程），它退出后自动回收，不能再被等待
+///
+/// 线程已经退出时立即回收。错误码与 `waittid` 相同。
+fn thread_detach(tid: usize) -> isize {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let process = unsafe { (*processor).get_current_proc().unwrap() };
+    let tid = match tid {
+        0 => unsafe { (*processor).current().unwrap().tid },
+        tid => ThreadId::from_usize(tid),
+    };
+    if process.detached.contains(&tid) {
+        return EINVAL;
+    }
+    if !process.joinable.remove(&tid) {
+        return ESRCH;
+    }
+    match unsafe { (*processor).waittid(tid) } {
+        Some(_) => process.stacks.free(tid, &mut process.address_space),
+        None => {
+            process.detached.insert(tid);
+        }
+    }
+    0
+}
+
+/// wait4：回收 `pid` 指定的子进程（约定见 `ProcTree::reap`），返回其 PID 并写回退出码
+///
+/// 带 `WUNTRACED` 时同时报告新停止的子进程，状态为 `WSTOPPED | SIGSTOP`。
+/// 子进程都还在运行时：带 `WNOHANG` 返回 0，否则阻塞到有线程退出或停止后重新检查。
+/// 没有匹配的子进程时返回 `LEGACY_ERR`。过继来的孤儿同样可以回收。
+fn wait4(pid: isize, status: usize, options: usize) -> isize {
+    let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+    let current = unsafe { (*processor).get_current_proc().unwrap() };
+    let reap = PROC_TREE.lock().reap(current.pid, pid, options & WUNTRACED != 0);
+    let (child, exit_code) = match reap {
+        Reap::Exited(child, exit_code) => {
+            // 同时清除 `PThreadManager` 内部的退出记录（过继来的孤儿没有）
+            unsafe { (*processor).wait(child) };
+            (child, exit_code as i32)
+        }
+        Reap::Stopped(child) => (child, WSTOPPED | SignalNo::SIGSTOP as i32),
+        Reap::Running if options & WNOHANG != 0 => return 0,
+        Reap::Running => {
+            processor::wait_for_exit(unsafe { (*processor).current().unwrap().tid });
+            return restart(Restart::Block);
+        }
+        Reap::NoChild => return LEGACY_ERR,
+    };
+    if status != 0 {
+        if let Some(mut ptr) = current.translate_mut::<i32>(status) {
+            unsafe { *ptr.as_mut() = exit_code };
+        }
+    }
+    child.get_usize() as isize
+}
+
+/// getppid：父进程 PID；过继后为 initproc 的 PID，initproc 本身没有父进程，返回 0（与 Linux 相同）
+fn getppid() -> isize {
+    let pid = PROCESSOR.get_mut().get_current_proc().unwrap().pid;
+    PROC_TREE.lock().parent(pid).map_or(0, |parent| parent.get_usize() as isize)
+}
+
+/// 当前进程的 `fd` 是否为可读的控制台（read 交给 `read_stdin`）
+fn reads_console(fd: usize) -> bool {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    current.fd_table.get(fd).is_some_and(|fd| {
+        fd.as_ref().is_some_and(|fd| matches!(*fd.lock(), Fd::Console { read: true, .. }))
+    })
+}
+
+/// read(控制台)：从控制台读取至多 `count` 个字节（不跨页），返回读到的字节数
+///
+/// 没有输入或调用者属于后台进程组时阻塞，见 `tty::read`。
+fn read_stdin(buf: usize, count: usize) -> isize {
+    const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+    let processor = PROCESSOR.get_mut();
+    let tid = processor.current().unwrap().tid;
+    let current = processor.get_current_proc().unwrap();
+    let count = count.min(PAGE_SIZE - buf % PAGE_SIZE);
+    if count == 0 {
+        return 0;
+    }
+    let Some(ptr) = current.translate_mut::<u8>(buf) else {
+        log::error!("sys_read: buffer at {buf:#x} not writeable");
+        return EFAULT;
+    };
+    let buf = unsafe { core::slice::from_raw_parts_mut(ptr.as_ptr(), count) };
+    match tty::read(current.pid, tid, buf) {
+        Some(len) => len as isize,
+        None => restart(Restart::Block),
+    }
+}
+
+/// 把 0 解释为调用者自身的 PID
+fn pid_or_self(pid: usize) -> ProcId {
+    match pid {
+        0 => PROCESSOR.get_mut().get_current_proc().unwrap().pid,
+        pid => ProcId::from_usize(pid),
+    }
+}
+
+/// setpgid：把自己或子进程 `pid` 移入进程组 `pgid`，二者为 0 时分别表示调用者和 `pid` 自身，不允许时返回 `EPERM`
+fn setpgid(pid: usize, pgid: usize) -> isize {
+    let caller = pid_or_self(0);
+    let pid = pid_or_self(pid);
+    let pgid = if pgid == 0 { pid } else { ProcId::from_usize(pgid) };
+    if PROC_TREE.lock().setpgid(caller, pid, pgid) { 0 } else { EPERM }
+}
+
+/// getpgid：进程 `pid`（0 表示调用者）的进程组 ID
+fn getpgid(pid: usize) -> isize {
+    PROC_TREE.lock().pgid(pid_or_self(pid)).map_or(ESRCH, |pgid| pgid.get_usize() as isize)
+}
+
+/// getsid：进程 `pid`（0 表示调用者）的会话 ID
+fn getsid(pid: usize) -> isize {
+    PROC_TREE.lock().sid(pid_or_self(pid)).map_or(ESRCH, |sid| sid.get_usize() as isize)
+}
+
+/// setsid：新建会话，调用者成为会话首进程和组长，返回新会话 ID；调用者已是组长时返回 `EPERM`
+fn setsid() -> isize {
+    let pid = pid_or_self(0);
+    if PROC_TREE.lock().setsid(pid) { pid.get_usize() as isize } else { EPERM }
+}
+
+/// tcgetpgrp：控制台的前台进程组；调用者不在控制台会话中时返回 `ENOTTY`
+fn tcgetpgrp() -> isize {
+    tty::foreground(pid_or_self(0)).map_or(ENOTTY, |pgid| pgid.get_usize() as isize)
+}
+
+/// tcsetpgrp：把控制台会话中的进程组 `pgid` 设为前台进程组，不允许时返回 `EPERM`
+fn tcsetpgrp(pgid: usize) -> isize {
+    let pid = pid_or_self(0);
+    if tty::set_foreground(PROCESSOR.get_mut(), pid, ProcId::from_usize(pgid)) { 0 } else { EPERM }
+}
+
+/// 从文件系统读取程序，找不到时打印可用程序列表
+fn read_program(name: &str) -> Option<Vec<u8>> {
+    let Some(file) = FS.open(name, OpenFlags::RDONLY) else {
+        log::error!("unknown app, select one in the list: ");
+        FS.readdir("").unwrap().into_iter().for_each(|app| println!("{app}"));
+        println!();
+        return None;
+    };
+    Some(read_all(file))
+}
+
+/// nanosleep：让当前线程睡眠 `req` 指定的时长
+///
+/// 第一次调用时把当前线程加入睡眠队列并阻塞，到期或被信号唤醒后重新执行：
+/// 已到期时返回 0；被信号提前唤醒时把剩余时长写入 `rem`（非空时）并返回 `EINTR`。
+fn nanosleep(req: usize, rem: usize) -> isize {
+    let processor = PROCESSOR.get_mut();
+    let now = timer::now();
+    let thread = processor.current().unwrap();
+    let tid = thread.tid;
+    if let Some(deadline) = thread.sleep_deadline.take() {
+        if now >= deadline {
+            return 0;
+        }
+        timer::cancel(tid);
+        if rem != 0 {
+            let current = processor.get_current_proc().unwrap();
+            let Some(mut ptr) = current.translate_mut::<TimeSpec>(rem) else {
+                return EFAULT;
+            };
+            unsafe { *ptr.as_mut() = timer::ticks_to_timespec(deadline - now) };
+        }
+        return EINTR;
+    }
+    let Some(ptr) = processor.get_current_proc().unwrap().translate::<TimeSpec>(req) else {
+        return EFAULT;
+    };
+    let req = unsafe { *ptr.as_ptr() };
+    if req.tv_nsec >= 1_000_000_000 {
+        return EINVAL;
+    }
+    let deadline = now + timer::timespec_to_ticks(&req);
+    processor.current().unwrap().sleep_deadline = Some(deadline);
+    timer::sleep_until(tid, deadline);
+    restart(Restart::Block)
+}
+
+/// getrlimit：读取当前进程对资源 `resource` 的限制
+fn getrlimit(resource: usize, buf: usize) -> isize {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    let Some(limit) = current.rlimits.get(resource) else {
+        return EINVAL;
+    };
+    match current.translate_mut::<RLimit>(buf) {
+        Some(mut ptr) => {
+            unsafe { *ptr.as_mut() = limit };
+            0
+        }
+        None => EFAULT,
+    }
+}
+
+/// setrlimit：设置当前进程对资源 `resource` 的限制，规则见 `ResourceLimits::set`
+///
+/// 资源不存在或软限制超过硬限制时返回 `EINVAL`，提高硬限制时返回 `EPERM`。
+fn setrlimit(resource: usize, buf: usize) -> isize {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    let Some(ptr) = current.translate::<RLimit>(buf) else {
+        return EFAULT;
+    };
+    let limit = unsafe { *ptr.as_ptr() };
+    if current.rlimits.set(resource, limit) {
+        return 0;
+    }
+    // 资源存在且软限制不超过硬限制时，失败只能是因为提高了硬限制
+    match current.rlimits.get(resource) {
+        Some(_) if limit.cur <= limit.max => EPERM,
+        _ => EINVAL,
+    }
+}
+
+/// getcpu：把调用线程所在的逻辑核号写入 `cpu`，NUMA 节点号（总是 0）写入 `node`，均为 `u32`
+///
+/// 地址为 0 的参数不写。线程随时可能被调度到其他核上，返回的只是调用时的核号。
+fn getcpu(cpu: usize, node: usize) -> isize {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    for (addr, value) in [(cpu, smp::this_cpu() as u32), (node, 0)] {
+        if addr == 0 {
+            continue;
+        }
+        match current.translate_mut::<u32>(addr) {
+            Some(mut ptr) => unsafe { *ptr.as_mut() = value },
+            None => return EFAULT,
+        }
+    }
+    0
+}
+
+/// getrusage：查询当前进程（`RUSAGE_SELF`）或当前线程（`RUSAGE_THREAD`）的资源使用
+fn getrusage(who: isize, buf: usize) -> isize {
+    let processor = PROCESSOR.get_mut();
+    let usage = match who {
+        RUSAGE_SELF => {
+            let process = processor.get_current_proc().unwrap();
+            let pid = process.pid;
+            let usage = process.usage;
+            RUsage::new(&usage, processor.get_thread(pid).map_or(0, |t| t.len()))
+        }
+        RUSAGE_THREAD => RUsage::new(&processor.current().unwrap().usage, 1),
+        _ => return EINVAL,
+    };
+    write_usage(buf, usage)
+}
+
+/// proc_usage：查询 PID 不小于 `pid` 的第一个存活进程的资源使用，返回其 PID
+///
+/// 用户程序从 0 开始、每次以上次返回值加一继续查询，即可遍历所有进程；
+/// 没有更多进程时返回 `ESRCH`。
+fn proc_usage(pid: usize, buf: usize) -> isize {
+    let processor = PROCESSOR.get_mut();
+    let Some(&pid) = PIDS.lock().range(ProcId::from_usize(pid)..).next() else {
+        return ESRCH;
+    };
+    let usage = processor.get_proc(pid).unwrap().usage;
+    let nthreads = processor.get_thread(pid).map_or(0, |t| t.len());
+    match write_usage(buf, RUsage::new(&usage, nthreads)) {
+        0 => pid.get_usize() as isize,
+        err => err,
+    }
+}
+
+/// 把统计结果写入当前进程地址空间中的 `buf`
+fn write_usage(buf: usize, usage: RUsage) -> isize {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    match current.translate_mut::<RUsage>(buf) {
+        Some(mut ptr) => {
+            unsafe { *ptr.as_mut() = usage };
+            0
+        }
+        None => EFAULT,
+    }
+}
+
+/// 用户地址区间 `[addr, addr + len)` 对应的页号区间，`addr` 未按页对齐或 `len` 为 0 时返回 `None`
+fn page_range(addr: usize, len: usize) -> Option<Range<usize>> {
+    let page_mask = (1 << Sv39::PAGE_BITS) - 1;
+    if addr & page_mask != 0 || len == 0 {
+        return None;
+    }
+    let end = addr.checked_add(len)?.checked_add(page_mask)?;
+    Some(addr >> Sv39::PAGE_BITS..end >> Sv39::PAGE_BITS)
+}
+
+/// sbrk：把堆顶移动 `size` 字节，返回旧的堆顶
+///
+/// 越过堆底、进入 mmap 区域或超过 `RLIMIT_AS` 时返回 `LEGACY_ERR`（与 ch4、ch5 相同）。
+fn sbrk(size: isize) -> isize {
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    match current.change_program_brk(size) {
+        Some(old_brk) => old_brk as isize,
+        None => LEGACY_ERR,
+    }
+}
+
+/// mmap：建立映射，返回映射的起始地址（约定见 `mmap` 模块）
+///
+/// 不带 `MAP_ANONYMOUS` 时映射文件 `fd` 从 `offset`（按页对齐）开始的内容；
+/// 匿名映射只支持 `MAP_PRIVATE`，忽略 `fd` 和 `offset`。
+///
+/// 不带 `MAP_FIXED` 时 `addr` 只是提示；`MAP_FIXED` 替换区间内原有的 mmap 映射，
+/// `MAP_FIXED_NOREPLACE` 在区间已被占用时失败，两者的区间都必须位于 mmap 区域内。
+fn mmap(addr: usize, len: usize, prot: usize, flags: usize, fd: isize, offset: usize) -> isize {
+    let Some(range) = page_range(addr, len) else {
+        return EINVAL;
+    };
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return EINVAL;
+    }
+    // MAP_SHARED 与 MAP_PRIVATE 必须恰好指定一个
+    let shared = match flags & (MAP_SHARED | MAP_PRIVATE) {
+        MAP_SHARED => true,
+        MAP_PRIVATE => false,
+        _ => return EINVAL,
+    };
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    let file = if flags & MAP_ANONYMOUS != 0 {
+        if shared {
+            return EINVAL;
+        }
+        None
+    } else {
+        if offset & ((1 << Sv39::PAGE_BITS) - 1) != 0 {
+            return EINVAL;
+        }
+        let Some(Some(fd)) = usize::try_from(fd).ok().and_then(|fd| current.fd_table.get(fd)) else {
+            return EBADF;
+        };
+        let fd = fd.lock();
+        let Fd::File(handle) = &*fd else {
+            return EBADF;
+        };
+        let Some(inode) = handle.inode.clone() else {
+            return EBADF;
+        };
+        // 私有映射的修改不写回，只要求可读；共享的可写映射还要求文件可写
+        if !handle.readable() || (shared && prot & PROT_WRITE != 0 && !handle.writable()) {
+            return EACCES;
+        }
+        Some(FileMapping::new(inode, offset, len, shared, handle.writable()))
+    };
+    let pages = range.len();
+    if !current.can_map(pages) {
+        return ENOMEM;
+    }
+    let start = if flags & (MAP_FIXED | MAP_FIXED_NOREPLACE) != 0 {
+        if !MemoryMap::in_range(&range) {
+            return ENOMEM;
+        }
+        if !current.mmap.is_free(&range) {
+            if flags & MAP_FIXED_NOREPLACE != 0 {
+                return EEXIST;
+            }
+            current.mmap.unmap(range.clone(), &mut current.address_space);
+        }
+        range.start
+    } else {
+        match current.mmap.find_free(range.start, pages) {
+            Some(start) => start,
+            None => return ENOMEM,
+        }
+    };
+    current.mmap.map(start..start + pages, prot, file, &mut current.address_space);
+    (start << Sv39::PAGE_BITS) as isize
+}
+
+/// munmap：取消 `[addr, addr + len)` 内的 mmap 映射，可以只取消一个区域的一部分
+fn munmap(addr: usize, len: usize) -> isize {
+    let Some(range) = page_range(addr, len) else {
+        return EINVAL;
+    };
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    current.mmap.unmap(range, &mut current.address_space);
+    0
+}
+
+/// mprotect：修改 `[addr, addr + len)` 的权限，区间必须全部是 mmap 映射
+fn mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    let Some(range) = page_range(addr, len) else {
+        return EINVAL;
+    };
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return EINVAL;
+    }
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    if prot & PROT_WRITE != 0 && current.mmap.write_denied(&range) {
+        return EACCES;
+    }
+    match current.mmap.protect(range, prot, &mut current.address_space) {
+        true => 0,
+        false => ENOMEM,
+    }
+}
+
+/// msync：把 `[addr, addr + len)` 内共享文件映射的修改写回文件，区间必须全部是 mmap 映射
+///
+/// 写回是同步完成的，`flags`（`MS_SYNC` / `MS_ASYNC` / `MS_INVALIDATE`）不影响行为。
+fn msync(addr: usize, len: usize, _flags: usize) -> isize {
+    let Some(range) = page_range(addr, len) else {
+        return EINVAL;
+    };
+    let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+    match current.mmap.sync(range, &mut current.address_space) {
+        true => 0,
+        false => ENOMEM,
+    }
+}
+
+/// 读取用户态以 NUL 结尾的字符串，同时从 `budget` 中扣除占用的字节数
+fn read_cstr(process: &mut Process, addr: usize, budget: &mut usize) -> Result<String, isize> {
+    let mut bytes = Vec::new();
+    loop {
+        let ptr = process.translate::<u8>(addr + bytes.len()).ok_or(EFAULT)?;
+        *budget = budget.checked_sub(1).ok_or(E2BIG)?;
+        match unsafe { *ptr.as_ptr() } {
+            0 => break,
+            ch => bytes.push(ch),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| EINVAL)
+}
+
+/// 读取用户态以 NULL 结尾的字符串指针数组；`addr` 为 0 时视为空数组
+fn read_cstr_array(
+    process: &mut Process,
+    addr: usize,
+    budget: &mut usize,
+) -> Result<Vec<String>, isize> {
+    let mut strings = Vec::new();
+    if addr == 0 {
+        return Ok(strings);
+    }
+    loop {
+        let slot = addr + strings.len() * core::mem::size_of::<usize>();
+        let ptr = process.translate::<usize>(slot).ok_or(EFAULT)?;
+        *budget = budget.checked_sub(core::mem::size_of::<usize>()).ok_or(E2BIG)?;
+        match unsafe { *ptr.as_ptr() } {
+            0 => break,
+            str_addr => strings.push(read_cstr(process, str_addr, budget)?),
+        }
+    }
+    Ok(strings)
+}
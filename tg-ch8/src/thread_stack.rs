@@ -0,0 +1,168 @@
+//! 线程栈分配器
+//!
+//! 每个进程的 `Process::stacks` 管理该进程中由 `thread_create` 创建的线程的用户栈
+//! （主线程的栈由 `Process::from_elf` 建立，不在这里管理）。
+//!
+//! 线程栈位于主线程栈下方，自高地址向低地址依次排列，每个栈的正下方留一个**不映射的保护页**。
+//! 主线程栈按需增长，为它预留的区域与线程栈之间隔开 `STACK_GUARD_PAGES` 页：
+//!
+//! ```text
+//! USER_STACK_TOP ┬─────────────┐
+//!                │  主线程栈   │ ← 按需增长，至多 USER_STACK_PAGES 页
+//!                ├─────────────┤
+//!                │   保护间隔  │
+//!                ├─────────────┤ ← 线程 1 的初始 sp
+//!                │  线程 1 栈  │
+//!                ├─────────────┤
+//!                │   保护页    │
+//!                ├─────────────┤ ← 线程 2 的初始 sp
+//!                │     ...     │
+//! ```
+//!
+//! 栈溢出时访问保护页产生缺页异常，进程收到 SIGSEGV，而不会悄悄改写相邻线程的栈。
+//!
+//! 线程被 `waittid` 回收时释放它的栈，释放的区间（含保护页）记入空闲表，
+//! 之后创建的线程优先复用；相邻的空闲区间会合并。进程退出时剩余的栈随地址空间一起释放。
+
+use crate::{
+    build_flags,
+    process::{STACK_GUARD_PAGES, USER_STACK_PAGES, USER_STACK_TOP},
+    Sv39, Sv39Manager,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+use tg_kernel_vm::{
+    page_table::{VAddr, VPN},
+    AddressSpace,
+};
+use tg_task_manage::ThreadId;
+
+/// `thread_create` 未指定大小时的线程栈页数
+pub const DEFAULT_STACK_PAGES: usize = 2;
+/// 线程栈区域的大小（页），所有线程栈及其保护页都在这个区域内
+const REGION_PAGES: usize = 1 << 20;
+
+/// 一个进程的线程栈分配器
+pub struct ThreadStacks {
+    /// 各线程的栈（页号区间，不含保护页）
+    stacks: BTreeMap<ThreadId, Range<usize>>,
+    /// 已释放、可以复用的区间（含保护页）：起始页号 → 结束页号
+    free: BTreeMap<usize, usize>,
+    /// 尚未分配过的区域的上界（页号）
+    top: usize,
+}
+
+impl ThreadStacks {
+    /// 空的分配器：第一个栈紧贴主线程栈下方的保护间隔
+    pub fn new() -> Self {
+        let main_bottom = VAddr::<Sv39>::new(USER_STACK_TOP).floor().val() - USER_STACK_PAGES;
+        let top = main_bottom - STACK_GUARD_PAGES;
+        Self { stacks: BTreeMap::new(), free: BTreeMap::new(), top }
+    }
+
+    /// 为线程 `tid` 分配 `pages` 页的栈并映射到 `space`，返回初始 sp（栈顶地址）
+    ///
+    /// 线程栈区域用尽时返回 `None`。
+    pub fn alloc(
+        &mut self,
+        tid: ThreadId,
+        pages: usize,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) -> Option<usize> {
+        // 每个栈连同下方的保护页一起占用 `pages + 1` 页
+        let need = pages.checked_add(1)?;
+        let slot = match self.free.iter().find(|(&start, &end)| end - start >= need) {
+            Some((&start, &end)) => {
+                self.free.remove(&start);
+                // 取空闲区间的高端，余下的低端仍是一个带保护页的空闲区间
+                if end - start > need {
+                    self.free.insert(start, end - need);
+                }
+                end - need..end
+            }
+            None => {
+                let floor = self.floor();
+                if self.top < floor + need {
+                    return None;
+                }
+                self.top -= need;
+                self.top..self.top + need
+            }
+        };
+        let stack = slot.start + 1..slot.end;
+        // 逐页映射，与 `from_elf` 一致，便于 fork 复制和逐页释放
+        for vpn in stack.clone() {
+            let vpn = VPN::<Sv39>::new(vpn);
+            space.map(vpn..vpn + 1, &[], 0, build_flags("U_WRV"));
+        }
+        let sp = VPN::<Sv39>::new(stack.end).base().val();
+        self.stacks.insert(tid, stack);
+        Some(sp)
+    }
+
+    /// 释放线程 `tid` 的栈（不是由本分配器创建的线程忽略）
+    pub fn free(&mut self, tid: ThreadId, space: &mut AddressSpace<Sv39, Sv39Manager>) {
+        let Some(stack) = self.stacks.remove(&tid) else {
+            return;
+        };
+        for vpn in stack.clone() {
+            let vpn = VPN::<Sv39>::new(vpn);
+            space.unmap(vpn..vpn + 1);
+        }
+        self.release(stack.start - 1..stack.end);
+    }
+
+    /// fork 时子进程的分配器
+    ///
+    /// 子进程只有调用 fork 的线程 `parent`，它在子进程中成为 `child`。
+    /// `space` 是子进程复制得到的地址空间，其中属于父进程其他线程的栈在这里释放。
+    pub fn fork(
+        &self,
+        parent: ThreadId,
+        child: ThreadId,
+        space: &mut AddressSpace<Sv39, Sv39Manager>,
+    ) -> Self {
+        let mut stacks = Self { stacks: self.stacks.clone(), free: self.free.clone(), top: self.top };
+        let mine = stacks.stacks.remove(&parent);
+        let others: Vec<ThreadId> = stacks.stacks.keys().copied().collect();
+        for tid in others {
+            stacks.free(tid, space);
+        }
+        if let Some(stack) = mine {
+            stacks.stacks.insert(child, stack);
+        }
+        stacks
+    }
+
+    /// 线程栈区域的下界（页号）
+    #[inline]
+    fn floor(&self) -> usize {
+        let top = VAddr::<Sv39>::new(USER_STACK_TOP).floor().val();
+        top - USER_STACK_PAGES - STACK_GUARD_PAGES - REGION_PAGES
+    }
+
+    /// 把区间 `slot` 放回空闲表，与相邻的空闲区间合并
+    fn release(&mut self, mut slot: Range<usize>) {
+        if let Some(end) = self.free.remove(&slot.end) {
+            slot.end = end;
+        }
+        if let Some((&start, &end)) = self.free.range(..slot.start).next_back() {
+            if end == slot.start {
+                self.free.remove(&start);
+                slot.start = start;
+            }
+        }
+        // 与尚未分配的区域相邻时直接归还
+        if slot.start == self.top {
+            self.top = slot.end;
+        } else {
+            self.free.insert(slot.start, slot.end);
+        }
+    }
+}
+
+impl Default for ThreadStacks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
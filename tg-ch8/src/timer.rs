@@ -0,0 +1,87 @@
+//! 时钟与睡眠队列
+//!
+//! `nanosleep` 不再让线程忙等：调用线程被阻塞（可以被信号打断），并以唤醒时刻为键加入 `SLEEPERS`
+//! （按截止时刻排序的小根堆）。调度循环每次进入时调用 `wake_expired` 把到期线程放回
+//! 就绪队列；进入用户态或空闲等待前调用 `program` 设置下一次时钟中断，
+//! 取 "时间片结束" 与 "最早的唤醒时刻" 中较早的一个。
+//!
+//! 睡眠队列与 `PROCESSOR` 一样只在持有 `smp::KERNEL_LOCK` 时访问。
+
+use crate::processor::{self, ProcessorInner};
+use alloc::collections::BinaryHeap;
+use core::cmp::Reverse;
+use riscv::register::time;
+use spin::Mutex;
+use tg_syscall::TimeSpec;
+use tg_task_manage::ThreadId;
+
+/// 时钟频率（QEMU virt 平台为 12.5 MHz）
+pub const CLOCK_FREQ: u64 = 12_500_000;
+
+/// 睡眠线程队列：(唤醒时刻, TID)，最早到期的在堆顶
+static SLEEPERS: Mutex<BinaryHeap<Reverse<(u64, ThreadId)>>> = Mutex::new(BinaryHeap::new());
+
+/// 读取当前时刻（时钟周期数）
+#[inline]
+pub fn now() -> u64 {
+    time::read() as u64
+}
+
+/// 把时间长度换算为时钟周期数（向上取整，保证至少睡够请求的时长）
+pub fn timespec_to_ticks(ts: &TimeSpec) -> u64 {
+    let sec = (ts.tv_sec as u64).saturating_mul(CLOCK_FREQ);
+    let nsec = (ts.tv_nsec as u64 * CLOCK_FREQ).div_ceil(1_000_000_000);
+    sec.saturating_add(nsec)
+}
+
+/// 把时钟周期数换算为时间长度
+pub fn ticks_to_timespec(ticks: u64) -> TimeSpec {
+    TimeSpec {
+        tv_sec: (ticks / CLOCK_FREQ) as usize,
+        tv_nsec: ((ticks % CLOCK_FREQ) * 1_000_000_000 / CLOCK_FREQ) as usize,
+    }
+}
+
+/// 让线程 `tid` 睡眠到 `deadline` 时刻
+///
+/// 调用者负责随后把该线程标记为阻塞态。
+pub fn sleep_until(tid: ThreadId, deadline: u64) {
+    SLEEPERS.lock().push(Reverse((deadline, tid)));
+}
+
+/// 把线程 `tid` 移出睡眠队列（被信号提前唤醒时调用，避免到期时误唤醒它的其他等待）
+pub fn cancel(tid: ThreadId) {
+    SLEEPERS.lock().retain(|&Reverse((_, sleeper))| sleeper != tid);
+}
+
+/// 最早的唤醒时刻
+#[inline]
+pub fn next_deadline() -> Option<u64> {
+    SLEEPERS.lock().peek().map(|Reverse((deadline, _))| *deadline)
+}
+
+/// 唤醒所有已到期的线程，返回唤醒的线程数
+///
+/// 睡眠期间已被回收（或已被其他原因唤醒）的线程直接丢弃。
+pub fn wake_expired(processor: &mut ProcessorInner) -> usize {
+    let now = now();
+    let mut sleepers = SLEEPERS.lock();
+    let mut woken = 0;
+    while let Some(&Reverse((deadline, tid))) = sleepers.peek() {
+        if deadline > now {
+            break;
+        }
+        sleepers.pop();
+        if processor::wake(processor, tid) {
+            woken += 1;
+        }
+    }
+    woken
+}
+
+/// 设置本核下一次时钟中断：`slice_end` 与最早唤醒时刻中较早者
+#[inline]
+pub fn program(slice_end: u64) {
+    let deadline = next_deadline().map_or(slice_end, |d| d.min(slice_end));
+    tg_sbi::set_timer(deadline);
+}
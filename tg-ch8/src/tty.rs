@@ -0,0 +1,146 @@
+//! 控制台终端
+//!
+//! 调度循环每次进入时调用 `poll`，把串口收到的字符放入输入缓冲：
+//!
+//! - Ctrl-C 向**前台进程组**发送 SIGINT，Ctrl-Z 发送 SIGSTOP，二者都不进入缓冲；
+//! - 读标准输入时缓冲为空，或调用者是控制台会话中的后台进程组，线程阻塞直到有新输入
+//!   或前台进程组改变（`tcsetpgrp`）。
+//!
+//! 控制台属于 initproc 所在的会话，前台进程组初始为 initproc 的进程组；
+//! 调用 `setsid` 离开该会话的进程不受前台进程组限制。
+//!
+//! 终端状态与 `PROCESSOR` 一样只在持有 `smp::KERNEL_LOCK` 时访问。
+
+use crate::{
+    proc_tree::PROC_TREE,
+    processor::{self, ProcessorInner},
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use spin::Mutex;
+use tg_signal::SignalNo;
+use tg_task_manage::{ProcId, ThreadId};
+
+/// Ctrl-C
+const CTRL_C: u8 = 0x03;
+/// Ctrl-Z
+const CTRL_Z: u8 = 0x1a;
+/// 输入缓冲的最大字节数，超出的输入被丢弃
+const INPUT_MAX: usize = 4096;
+
+/// 控制台终端
+pub struct Tty {
+    /// 尚未被读取的输入
+    input: VecDeque<u8>,
+    /// 等待输入的线程
+    readers: Vec<ThreadId>,
+    /// 控制台所属的会话
+    session: Option<ProcId>,
+    /// 前台进程组
+    foreground: Option<ProcId>,
+}
+
+impl Tty {
+    const fn new() -> Self {
+        Self { input: VecDeque::new(), readers: Vec::new(), session: None, foreground: None }
+    }
+}
+
+/// 全局控制台终端
+static TTY: Mutex<Tty> = Mutex::new(Tty::new());
+
+/// 把控制台交给进程 `pid` 所在的会话，其进程组成为前台进程组
+pub fn attach(pid: ProcId) {
+    let mut tty = TTY.lock();
+    let tree = PROC_TREE.lock();
+    tty.session = tree.sid(pid);
+    tty.foreground = tree.pgid(pid);
+}
+
+/// 读取串口输入并处理控制字符，返回唤醒的线程数
+pub fn poll(processor: &mut ProcessorInner) -> usize {
+    let mut tty = TTY.lock();
+    let mut signal = None;
+    let mut received = false;
+    loop {
+        // 没有输入时返回 usize::MAX（部分实现返回 0）
+        let c = tg_sbi::console_getchar();
+        if c == usize::MAX || c == 0 {
+            break;
+        }
+        match c as u8 {
+            CTRL_C => signal = Some(SignalNo::SIGINT),
+            CTRL_Z => signal = Some(SignalNo::SIGSTOP),
+            c if tty.input.len() < INPUT_MAX => {
+                tty.input.push_back(c);
+                received = true;
+            }
+            _ => {}
+        }
+    }
+    if let (Some(signal), Some(pgid)) = (signal, tty.foreground) {
+        let group = PROC_TREE.lock().group(pgid);
+        for pid in group {
+            processor::send_signal(pid, signal);
+        }
+    }
+    // 收到信号时同样唤醒读者，使它们回到内核处理信号
+    if received || signal.is_some() {
+        wake_readers(&mut tty, processor)
+    } else {
+        0
+    }
+}
+
+/// 从控制台读取至多 `buf.len()` 个字节
+///
+/// 没有可读的输入，或 `pid` 属于控制台会话中的后台进程组时，
+/// 把线程 `tid` 登记为读者并返回 `None`，调用者负责随后阻塞该线程。
+pub fn read(pid: ProcId, tid: ThreadId, buf: &mut [u8]) -> Option<usize> {
+    let mut tty = TTY.lock();
+    let tree = PROC_TREE.lock();
+    let background =
+        tty.session.is_some() && tree.sid(pid) == tty.session && tree.pgid(pid) != tty.foreground;
+    if background || tty.input.is_empty() {
+        tty.readers.push(tid);
+        return None;
+    }
+    let len = buf.len().min(tty.input.len());
+    for (dst, src) in buf.iter_mut().zip(tty.input.drain(..len)) {
+        *dst = src;
+    }
+    Some(len)
+}
+
+/// tcgetpgrp：前台进程组；`pid` 不在控制台会话中时返回 `None`
+pub fn foreground(pid: ProcId) -> Option<ProcId> {
+    let tty = TTY.lock();
+    if tty.session.is_none() || PROC_TREE.lock().sid(pid) != tty.session {
+        return None;
+    }
+    tty.foreground
+}
+
+/// tcsetpgrp：把控制台会话中的进程组 `pgid` 设为前台进程组
+///
+/// `pid`（调用者）必须在控制台会话中。被阻塞的读者全部唤醒，重新检查自己是否在前台。
+pub fn set_foreground(processor: &mut ProcessorInner, pid: ProcId, pgid: ProcId) -> bool {
+    let mut tty = TTY.lock();
+    let Some(session) = tty.session else {
+        return false;
+    };
+    {
+        let tree = PROC_TREE.lock();
+        if tree.sid(pid) != Some(session) || !tree.group_in_session(pgid, session) {
+            return false;
+        }
+    }
+    tty.foreground = Some(pgid);
+    wake_readers(&mut tty, processor);
+    true
+}
+
+/// 唤醒所有读者，返回唤醒的线程数
+fn wake_readers(tty: &mut Tty, processor: &mut ProcessorInner) -> usize {
+    let readers = core::mem::take(&mut tty.readers);
+    readers.into_iter().filter(|&tid| processor::wake(processor, tid)).count()
+}
@@ -32,6 +32,12 @@ SECTIONS {{
         *(.data .data.*)
         *(.sdata .sdata.*)
     }}
+    .tdata : {{
+        *(.tdata .tdata.*)
+    }}
+    .tbss : {{
+        *(.tbss .tbss.*)
+    }}
     .bss : {{
         *(.bss .bss.*)
         *(.sbss .sbss.*)
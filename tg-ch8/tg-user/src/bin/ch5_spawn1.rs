@@ -8,7 +8,7 @@ use user_lib::{spawn, wait, waitpid};
 
 #[no_mangle]
 extern "C" fn main() -> i32 {
-    let cpid = spawn("ch5_exit0");
+    let cpid = spawn("ch5_exit0", &["ch5_exit0"]);
     assert!(cpid >= 0, "child pid invalid");
     println!("new child {}", cpid);
     let mut exit_code: i32 = 0;
@@ -17,7 +17,7 @@ extern "C" fn main() -> i32 {
     assert_eq!(exit_code, 66778, "error exit code");
     println!("Test wait OK!");
 
-    let (cpid0, cpid1) = (spawn("ch5_exit0"), spawn("ch5_exit1"));
+    let (cpid0, cpid1) = (spawn("ch5_exit0", &["ch5_exit0"]), spawn("ch5_exit1", &["ch5_exit1"]));
     let exit_pid = waitpid(cpid1, &mut exit_code);
     assert_eq!(exit_pid, cpid1, "error exit pid");
     assert_eq!(exit_code, -233, "error exit code");
@@ -18,7 +18,7 @@ static TESTS: &[&str] = &[
 extern "C" fn main() -> i32 {
     let mut pid = [0isize; 6];
     for (i, test) in TESTS.iter().enumerate() {
-        pid[i] = spawn(*test);
+        pid[i] = spawn(*test, &[*test]);
     }
     set_priority(4);
     for i in 0..6 {
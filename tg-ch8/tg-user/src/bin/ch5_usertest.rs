@@ -36,7 +36,7 @@ extern "C" fn main() -> i32 {
     let mut pid = [0isize; 20];
     for (i, &test) in TESTS.iter().enumerate() {
         println!("Usertests: Running {}", test);
-        pid[i] = spawn(test);
+        pid[i] = spawn(test, &[test]);
     }
     let mut xstate: i32 = Default::default();
     for (i, &test) in TESTS.iter().enumerate() {
@@ -48,7 +48,7 @@ extern "C" fn main() -> i32 {
         assert_eq!(pid[i], wait_pid);
     }
     println!("Usertests: Running {}", STEST);
-    let spid = spawn(STEST);
+    let spid = spawn(STEST, &[STEST]);
     xstate = Default::default();
     let wait_pid = waitpid(spid, &mut xstate);
     assert_eq!(spid, wait_pid);
@@ -42,7 +42,7 @@ static TESTS: &[&str] = &[
 extern "C" fn main() -> i32 {
     for test in TESTS {
         println!("Usertests: Running {}", test);
-        let pid = spawn(*test);
+        let pid = spawn(*test, &[*test]);
         let mut xstate: i32 = Default::default();
         let wait_pid = waitpid(pid, &mut xstate);
         assert_eq!(pid, wait_pid);
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, execve, fork, waitpid};
+
+const ARGV: [&str; 3] = ["ch8_args", "hello", "world"];
+const ENVP: [&str; 1] = ["GREETING=hi"];
+
+/// 读取以 NUL 结尾的字符串
+unsafe fn cstr(ptr: *const u8) -> &'static str {
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len))
+}
+
+#[no_mangle]
+pub extern "C" fn main(argc: usize, argv: *const *const u8) -> i32 {
+    if argc == 1 {
+        // 第一次运行：带参数重新执行自己，检查子进程看到的参数
+        let pid = fork();
+        if pid == 0 {
+            execve(ARGV[0], &ARGV, &ENVP);
+            panic!("execve failed");
+        }
+        let mut exit_code = 0;
+        assert_eq!(waitpid(pid, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+        println!("ch8 args test passed!");
+        return 0;
+    }
+    assert_eq!(argc, ARGV.len());
+    assert_eq!(args(), ARGV);
+    for (i, arg) in args().iter().enumerate() {
+        println!("argv[{}] = {}", i, arg);
+    }
+    // System V 布局：envp 紧跟在 argv 的 NULL 之后
+    unsafe {
+        assert!((*argv.add(argc)).is_null());
+        let envp = argv.add(argc + 1);
+        assert_eq!(cstr(*envp), ENVP[0]);
+        assert!((*envp.add(1)).is_null());
+    }
+    0
+}
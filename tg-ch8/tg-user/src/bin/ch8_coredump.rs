@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::format;
+use user_lib::{
+    close, exit, fork, open, read, setrlimit, thread_create, waitpid, wcoredump, wifsignaled,
+    wtermsig, OpenFlags, RLimit, SignalNo, RLIMIT_CORE, RLIM_INFINITY,
+};
+
+/// 访问未映射的地址
+fn segfault() -> ! {
+    let ptr = core::hint::black_box(0x10 as *mut usize);
+    unsafe { ptr.write_volatile(1) };
+    unreachable!();
+}
+
+fn spin(_arg: usize) -> isize {
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// 在子进程中运行 `f`，返回子进程的 PID 和退出状态
+fn run(f: fn()) -> (isize, i32) {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    (pid, status)
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 多线程进程出错：写出 core.<pid>
+    let (pid, status) = run(|| {
+        thread_create(spin as *const () as usize, 0);
+        segfault();
+    });
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SignalNo::SIGSEGV as i32);
+    assert!(wcoredump(status));
+
+    let fd = open(&format!("core.{pid}\0"), OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut head = [0u8; 64 + 56];
+    assert_eq!(read(fd as usize, &mut head), head.len() as isize);
+    close(fd as usize);
+    // ELF 头：64 位小端 ET_CORE，RISC-V
+    assert_eq!(&head[..6], b"\x7fELF\x02\x01");
+    assert_eq!(u16_at(&head, 16), 4);
+    assert_eq!(u16_at(&head, 18), 243);
+    // 至少有 PT_NOTE 和一个 PT_LOAD，第一个程序头是 PT_NOTE
+    assert!(u16_at(&head, 56) >= 2);
+    assert_eq!(u16_at(&head, 64), 4);
+
+    // RLIMIT_CORE 为 0：不转储
+    let (_, status) = run(|| {
+        setrlimit(RLIMIT_CORE, &RLimit { cur: 0, max: RLIM_INFINITY });
+        segfault();
+    });
+    assert!(wifsignaled(status));
+    assert!(!wcoredump(status));
+
+    println!("ch8 coredump test passed!");
+    0
+}
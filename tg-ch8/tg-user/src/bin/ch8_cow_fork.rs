@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{
+    close, exit, fork, getrlimit, pipe, read, sleep, thread_create, waitpid, waittid, write,
+    RLimit, RLIMIT_NPROC,
+};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 8;
+/// 同时存在的子进程数
+const CHILDREN: usize = 32;
+
+/// 跨越多页的可写数据，fork 后由父子进程共享物理页
+static mut DATA: [usize; PAGES * PAGE_SIZE / 8] = [0; PAGES * PAGE_SIZE / 8];
+/// 父进程的另一个线程不断修改的计数器
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static STOP: AtomicBool = AtomicBool::new(false);
+
+fn data() -> &'static mut [usize] {
+    unsafe { &mut *core::ptr::addr_of_mut!(DATA) }
+}
+
+/// 每页第一个字
+fn page(i: usize) -> &'static mut usize {
+    &mut data()[i * PAGE_SIZE / 8]
+}
+
+fn counter(_: usize) -> isize {
+    while !STOP.load(Ordering::Relaxed) {
+        COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+    exit(0)
+}
+
+fn wait_exit(pid: isize) -> i32 {
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    status
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    for i in 0..PAGES {
+        *page(i) = i;
+    }
+
+    // 子进程看到 fork 时的内容；双方此后的写入互不可见
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(fds[1]);
+        let mut byte = [0u8];
+        // 等父进程写过之后再读
+        assert_eq!(read(fds[0], &mut byte), 1);
+        for i in 0..PAGES {
+            assert_eq!(*page(i), i);
+        }
+        *page(0) = 100;
+        *page(PAGES - 1) = 200;
+        assert_eq!(*page(0), 100);
+        exit(0);
+    }
+    close(fds[0]);
+    *page(1) = 101;
+    assert_eq!(write(fds[1], b"x"), 1);
+    close(fds[1]);
+    assert_eq!(wait_exit(pid), 0);
+    assert_eq!(*page(0), 0);
+    assert_eq!(*page(1), 101);
+    assert_eq!(*page(PAGES - 1), PAGES - 1);
+    *page(PAGES - 1) = 300;
+    assert_eq!(*page(PAGES - 1), 300);
+
+    // 内核写入共享的页（栈上的输出参数）时同样先复制
+    let pid = fork();
+    if pid == 0 {
+        let mut limit = RLimit { cur: 0, max: 0 };
+        assert_eq!(getrlimit(RLIMIT_NPROC, &mut limit), 0);
+        assert!(limit.cur > 0);
+        exit(7);
+    }
+    let mut limit = RLimit { cur: 0, max: 0 };
+    assert_eq!(wait_exit(pid), 7);
+    assert_eq!(limit.cur, 0);
+    assert_eq!(getrlimit(RLIMIT_NPROC, &mut limit), 0);
+
+    // 多个子进程同时共享同一批页
+    let mut pids = [0isize; CHILDREN];
+    for (n, pid) in pids.iter_mut().enumerate() {
+        *pid = fork();
+        if *pid == 0 {
+            sleep(10);
+            assert_eq!(*page(1), 101);
+            *page(1) = n;
+            exit(n as i32);
+        }
+        assert!(*pid > 0);
+    }
+    for (n, &pid) in pids.iter().enumerate() {
+        assert_eq!(wait_exit(pid), n as i32);
+    }
+    assert_eq!(*page(1), 101);
+
+    // 父进程的其他线程在 fork 之后的写入不会出现在子进程中
+    let tid = thread_create(counter as *const () as usize, 0);
+    while COUNTER.load(Ordering::Relaxed) == 0 {}
+    let pid = fork();
+    if pid == 0 {
+        let seen = COUNTER.load(Ordering::Relaxed);
+        sleep(20);
+        assert_eq!(COUNTER.load(Ordering::Relaxed), seen);
+        exit(0);
+    }
+    assert_eq!(wait_exit(pid), 0);
+    STOP.store(true, Ordering::Relaxed);
+    assert_eq!(waittid(tid as usize), 0);
+
+    println!("ch8 cow fork test passed!");
+    0
+}
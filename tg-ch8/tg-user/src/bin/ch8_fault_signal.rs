@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, sigaction, sigprocmask, waitpid, wifsignaled, wtermsig, SignalAction, SignalNo,
+};
+
+/// SIGSEGV 处理函数运行后子进程的退出码
+const HANDLED: i32 = 42;
+
+/// 访问未映射的地址
+fn segfault() -> ! {
+    let ptr = core::hint::black_box(0x10 as *mut usize);
+    unsafe { ptr.write_volatile(1) };
+    unreachable!();
+}
+
+/// 执行非法指令
+fn illegal() -> ! {
+    unsafe { core::arch::asm!("unimp") };
+    unreachable!();
+}
+
+fn on_segv() {
+    // 出错的指令无法修复，处理函数直接结束进程
+    exit(HANDLED);
+}
+
+/// 在子进程中运行 `f`，返回子进程的退出状态
+fn run(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    status
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 没有处理函数：进程被 SIGSEGV 终止
+    let status = run(|| segfault());
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SignalNo::SIGSEGV as i32);
+
+    // 非法指令：进程被 SIGILL 终止
+    let status = run(|| illegal());
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SignalNo::SIGILL as i32);
+
+    // 用户处理函数可以接管缺页异常
+    let status = run(|| {
+        let mut action = SignalAction::default();
+        action.handler = on_segv as *const () as usize;
+        sigaction(SignalNo::SIGSEGV, &action, &SignalAction::default());
+        segfault();
+    });
+    assert!(!wifsignaled(status));
+    assert_eq!(status, HANDLED);
+
+    // 屏蔽 SIGSEGV 不能让出错的进程继续运行
+    let status = run(|| {
+        sigprocmask(1 << SignalNo::SIGSEGV as usize);
+        segfault();
+    });
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SignalNo::SIGSEGV as i32);
+
+    println!("ch8 fault signal test passed!");
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+use user_lib::{exit, fork, sbrk, waitpid};
+
+/// 超过初始 4 MiB 托管空间的单次分配
+const BIG: usize = 8 << 20;
+/// 小块分配的个数
+const BOXES: usize = 4096;
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // sbrk 返回旧的堆顶；扩展的页可读写，内容为 0
+    let origin = sbrk(0);
+    assert!(origin > 0);
+    assert_eq!(sbrk(4096), origin);
+    assert_eq!(sbrk(0), origin + 4096);
+    let page = unsafe { core::slice::from_raw_parts_mut(origin as *mut u8, 4096) };
+    assert!(page.iter().all(|&b| b == 0));
+    page.fill(1);
+    assert_eq!(sbrk(-4096), origin + 4096);
+    // 堆顶不能低于堆底
+    assert_eq!(sbrk(-(1 << 30)), -1);
+    assert_eq!(sbrk(0), origin);
+
+    // 分配器在托管空间不足时通过 sbrk 扩展
+    let mut big = Vec::<u8>::with_capacity(BIG);
+    big.resize(BIG, 0);
+    for (i, b) in big.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    assert!(big.iter().enumerate().all(|(i, &b)| b == i as u8));
+    assert!(sbrk(0) > origin);
+    let boxes: Vec<Box<[usize; 64]>> = (0..BOXES).map(|i| Box::new([i; 64])).collect();
+    assert!(boxes.iter().enumerate().all(|(i, b)| b[63] == i));
+    drop(big);
+
+    // 子进程继承堆，双方的修改互不可见
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(boxes[1][0], 1);
+        let mut more = Vec::<u8>::with_capacity(BIG);
+        more.resize(BIG, 0xff);
+        exit(more[BIG - 1] as i32 & 0x7f);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0x7f);
+    assert!(boxes.iter().enumerate().all(|(i, b)| b[0] == i));
+
+    println!("ch8 heap grow test passed!");
+    0
+}
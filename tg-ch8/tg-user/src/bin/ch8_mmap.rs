@@ -0,0 +1,115 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork,
+    mman::{
+        mmap, mmap_anonymous, mprotect, munmap, EEXIST, ENOMEM, MAP_ANONYMOUS,
+        MAP_FIXED_NOREPLACE, MAP_PRIVATE, PROT_NONE, PROT_READ, PROT_WRITE,
+    },
+    waitpid, wifsignaled, wtermsig, SignalNo,
+};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 4;
+
+fn page(base: usize, i: usize) -> *mut usize {
+    (base + i * PAGE_SIZE) as *mut usize
+}
+
+/// 在子进程中访问 `addr`，返回子进程是否被 SIGSEGV 终止
+fn faults(addr: usize, write: bool) -> bool {
+    let pid = fork();
+    if pid == 0 {
+        let ptr = addr as *mut usize;
+        unsafe {
+            if write {
+                ptr.write_volatile(1);
+            } else {
+                core::hint::black_box(ptr.read_volatile());
+            }
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    wifsignaled(status) && wtermsig(status) == SignalNo::SIGSEGV as i32
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 匿名映射可读写，初始内容为 0
+    let base = mmap_anonymous(PAGES * PAGE_SIZE, PROT_READ | PROT_WRITE);
+    assert!(base > 0 && base as usize % PAGE_SIZE == 0);
+    let base = base as usize;
+    for i in 0..PAGES {
+        unsafe {
+            assert_eq!(*page(base, i), 0);
+            *page(base, i) = i + 100;
+        }
+    }
+
+    // 占用的区间不会被再次分配
+    let other = mmap_anonymous(PAGE_SIZE, PROT_READ | PROT_WRITE);
+    assert!(other > 0);
+    let other = other as usize;
+    assert!(other + PAGE_SIZE <= base || other >= base + PAGES * PAGE_SIZE);
+    let flags = MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED_NOREPLACE;
+    assert_eq!(mmap(base, PAGE_SIZE, PROT_READ, flags, -1, 0), EEXIST);
+    assert_eq!(munmap(other, PAGE_SIZE), 0);
+
+    // 取消中间一页：其余页不受影响，访问这一页产生 SIGSEGV
+    assert_eq!(munmap(base + PAGE_SIZE, PAGE_SIZE), 0);
+    assert!(faults(base + PAGE_SIZE, false));
+    unsafe {
+        assert_eq!(*page(base, 0), 100);
+        assert_eq!(*page(base, 2), 102);
+    }
+    // 空洞不能 mprotect，可以用 MAP_FIXED_NOREPLACE 重新填上
+    assert_eq!(mprotect(base, 3 * PAGE_SIZE, PROT_READ), ENOMEM);
+    let hole = base + PAGE_SIZE;
+    assert_eq!(mmap(hole, PAGE_SIZE, PROT_READ | PROT_WRITE, flags, -1, 0), hole as isize);
+    unsafe { assert_eq!(*page(base, 1), 0) };
+
+    // 只读：可以读，写入产生 SIGSEGV；恢复可写后内容不变
+    assert_eq!(mprotect(base + 2 * PAGE_SIZE, PAGE_SIZE, PROT_READ), 0);
+    assert!(!faults(base + 2 * PAGE_SIZE, false));
+    assert!(faults(base + 2 * PAGE_SIZE, true));
+    assert!(!faults(base + 3 * PAGE_SIZE, true));
+    assert_eq!(mprotect(base + 2 * PAGE_SIZE, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    unsafe {
+        assert_eq!(*page(base, 2), 102);
+        *page(base, 2) = 202;
+    }
+
+    // PROT_NONE：读也会出错，但内容保留
+    assert_eq!(mprotect(base, PAGE_SIZE, PROT_NONE), 0);
+    assert!(faults(base, false));
+    assert_eq!(mprotect(base, PAGE_SIZE, PROT_READ), 0);
+    unsafe { assert_eq!(*page(base, 0), 100) };
+
+    // fork 的子进程得到映射的副本
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            assert_eq!(*page(base, 2), 202);
+            *page(base, 3) = 0;
+        }
+        exit(7);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    assert_eq!(status, 7);
+    unsafe { assert_eq!(*page(base, 3), 103) };
+
+    // 超过 RLIMIT_AS 的映射失败
+    assert_eq!(mmap_anonymous(1 << 30, PROT_READ | PROT_WRITE), ENOMEM);
+
+    assert_eq!(munmap(base, PAGES * PAGE_SIZE), 0);
+    assert!(faults(base, false));
+    println!("ch8 mmap test passed!");
+    0
+}
@@ -0,0 +1,167 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork,
+    mman::{
+        mmap, mprotect, msync, munmap, EACCES, EBADF, MAP_PRIVATE, MAP_SHARED, MS_SYNC,
+        PROT_READ, PROT_WRITE,
+    },
+    open, pipe, pipe_read, pipe_write, read, waitpid, write, OpenFlags,
+};
+
+const PAGE_SIZE: usize = 4096;
+/// 文件长度：一整页加第二页的前 100 字节
+const LEN: usize = PAGE_SIZE + 100;
+const NAME: &str = "mmap_file\0";
+
+/// 写出测试文件：第一页为 b'a'，第二页为 b'b'
+fn create() {
+    let fd = open(NAME, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let mut data = [b'a'; LEN];
+    data[PAGE_SIZE..].fill(b'b');
+    assert_eq!(write(fd as usize, &data), LEN as isize);
+    close(fd as usize);
+}
+
+/// 通过 read 读出整个文件，返回读到的字节数
+fn contents(buf: &mut [u8]) -> usize {
+    let fd = open(NAME, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut len = 0;
+    loop {
+        let n = read(fd as usize, &mut buf[len..]);
+        assert!(n >= 0);
+        if n == 0 {
+            break;
+        }
+        len += n as usize;
+    }
+    close(fd as usize);
+    len
+}
+
+/// 以 `flags` 打开测试文件并映射全部内容
+fn map(flags: OpenFlags, prot: usize, map_flags: usize) -> (usize, *mut u8) {
+    let fd = open(NAME, flags);
+    assert!(fd > 0);
+    let addr = mmap(0, LEN, prot, map_flags, fd, 0);
+    assert!(addr > 0, "mmap failed: {addr}");
+    (fd as usize, addr as *mut u8)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    create();
+    let mut buf = [0u8; 2 * PAGE_SIZE];
+
+    // 页在第一次访问时才读入：映射后、访问前对文件的修改可以看到
+    let (fd, ptr) = map(OpenFlags::RDWR, PROT_READ, MAP_PRIVATE);
+    assert_eq!(write(fd, b"lazy"), 4);
+    unsafe {
+        assert_eq!(core::slice::from_raw_parts(ptr, 4), b"lazy");
+        assert_eq!(*ptr.add(PAGE_SIZE), b'b');
+        // 超出文件末尾的部分为 0
+        assert_eq!(*ptr.add(LEN), 0);
+        assert_eq!(*ptr.add(2 * PAGE_SIZE - 1), 0);
+    }
+    assert_eq!(munmap(ptr as usize, LEN), 0);
+    close(fd);
+    create();
+
+    // 尚未调入的页可以直接作为系统调用的缓冲区：内核代替进程访问时同样从文件读入
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (fd, ptr) = map(OpenFlags::RDWR, PROT_READ | PROT_WRITE, MAP_SHARED);
+    unsafe {
+        assert_eq!(pipe_write(fds[1], core::slice::from_raw_parts(ptr.add(PAGE_SIZE), 4)), 4);
+        assert_eq!(pipe_read(fds[0], &mut buf[..4]), 4);
+        assert_eq!(&buf[..4], b"bbbb");
+        // 内核写入的共享页同样记为脏页，munmap 时写回
+        assert_eq!(pipe_write(fds[1], b"kern"), 4);
+        assert_eq!(pipe_read(fds[0], core::slice::from_raw_parts_mut(ptr, 4)), 4);
+    }
+    assert_eq!(munmap(ptr as usize, LEN), 0);
+    close(fd);
+    close(fds[0]);
+    close(fds[1]);
+    assert_eq!(contents(&mut buf), LEN);
+    assert_eq!(&buf[..4], b"kern");
+    create();
+
+    // 私有映射：修改只在进程内可见，不写回文件
+    let (fd, ptr) = map(OpenFlags::RDONLY, PROT_READ | PROT_WRITE, MAP_PRIVATE);
+    unsafe {
+        *ptr = b'x';
+        *ptr.add(PAGE_SIZE) = b'y';
+        assert_eq!(*ptr, b'x');
+    }
+    assert_eq!(msync(ptr as usize, LEN, MS_SYNC), 0);
+    assert_eq!(munmap(ptr as usize, LEN), 0);
+    assert_eq!(contents(&mut buf), LEN);
+    assert_eq!(buf[0], b'a');
+    assert_eq!(buf[PAGE_SIZE], b'b');
+
+    // 只读打开的文件不能建立可写的共享映射，也不能用 mprotect 改为可写
+    assert_eq!(mmap(0, LEN, PROT_READ | PROT_WRITE, MAP_SHARED, fd as isize, 0), EACCES);
+    let addr = mmap(0, LEN, PROT_READ, MAP_SHARED, fd as isize, 0);
+    assert!(addr > 0);
+    assert_eq!(mprotect(addr as usize, LEN, PROT_READ | PROT_WRITE), EACCES);
+    assert_eq!(munmap(addr as usize, LEN), 0);
+    close(fd);
+    // 不是打开的文件
+    assert_eq!(mmap(0, LEN, PROT_READ, MAP_SHARED, 100, 0), EBADF);
+
+    // 共享映射：msync 后修改出现在文件中
+    let (fd, ptr) = map(OpenFlags::RDWR, PROT_READ | PROT_WRITE, MAP_SHARED);
+    unsafe { *ptr.add(1) = b'm' };
+    assert_eq!(msync(ptr as usize, LEN, MS_SYNC), 0);
+    assert_eq!(contents(&mut buf), LEN);
+    assert_eq!(&buf[..2], b"am");
+    // 写回后页仍然可写，再次修改在 munmap 时写回；写回不改变文件长度
+    unsafe {
+        *ptr = b'M';
+        *ptr.add(PAGE_SIZE + 1) = b'n';
+    }
+    assert_eq!(munmap(ptr as usize, LEN), 0);
+    assert_eq!(contents(&mut buf), LEN);
+    assert_eq!(&buf[..2], b"Mm");
+    assert_eq!(&buf[PAGE_SIZE..PAGE_SIZE + 2], b"bn");
+
+    // fork 后父子共用共享映射的物理页：子进程的写入直接出现在父进程的映射中，进程结束时写回文件
+    let addr = mmap(0, LEN, PROT_READ | PROT_WRITE, MAP_SHARED, fd as isize, 0);
+    assert!(addr > 0);
+    let ptr = addr as *mut u8;
+    // 父进程先调入两页：之后看到的内容只能来自共用的物理页，而不是重新读文件
+    unsafe {
+        assert_eq!(*ptr.add(2), b'a');
+        assert_eq!(*ptr.add(PAGE_SIZE + 2), b'b');
+    }
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            *ptr.add(2) = b'c';
+            *ptr.add(PAGE_SIZE + 2) = b'd';
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid);
+    assert_eq!(status, 0);
+    unsafe {
+        assert_eq!(*ptr.add(2), b'c');
+        assert_eq!(*ptr.add(PAGE_SIZE + 2), b'd');
+    }
+    assert_eq!(contents(&mut buf), LEN);
+    assert_eq!(&buf[..3], b"Mmc");
+    assert_eq!(&buf[PAGE_SIZE..PAGE_SIZE + 3], b"bnd");
+    assert_eq!(munmap(addr as usize, LEN), 0);
+    close(fd);
+
+    println!("ch8 mmap file test passed!");
+    0
+}
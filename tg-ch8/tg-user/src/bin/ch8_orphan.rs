@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, getppid, sleep, wait4, waitpid, WNOHANG};
+
+/// 子进程的退出码
+const CHILD_EXIT: i32 = 13;
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        // 子进程：再创建一个孙进程，随后立即退出
+        let parent = getpid();
+        if fork() == 0 {
+            assert_eq!(getppid(), parent);
+            // 父进程退出后，孙进程被过继给 initproc
+            while getppid() == parent {
+                sleep(10);
+            }
+            println!("orphan reparented to pid {}", getppid());
+            exit(0);
+        }
+        exit(CHILD_EXIT);
+    }
+    // 子进程退出后成为僵尸，稍后回收仍能拿到退出码
+    sleep(100);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, CHILD_EXIT);
+    // 孙进程已过继给 initproc，不是本进程的子进程
+    assert_eq!(wait4(-1, &mut exit_code, WNOHANG), -1);
+    println!("ch8 orphan test passed!");
+    0
+}
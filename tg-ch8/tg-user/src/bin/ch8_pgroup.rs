@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpgid, getpid, getsid, kill, setpgid, setsid, sleep, wait4, waitpid, wifstopped,
+    SignalNo, EPERM, WUNTRACED,
+};
+
+/// 子进程：一直睡眠，直到被信号终止
+fn spin() -> ! {
+    loop {
+        sleep(10);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let pgid = getpgid(0);
+    let sid = getsid(0);
+    assert!(pgid > 0 && sid > 0);
+
+    // 子进程 leader 自成一个进程组；组长不能再新建会话
+    let leader = fork();
+    if leader == 0 {
+        assert_eq!(setpgid(0, 0), 0);
+        assert_eq!(getpgid(0), getpid());
+        assert_eq!(setsid(), EPERM);
+        spin();
+    }
+    assert_eq!(setpgid(leader as usize, leader as usize), 0);
+    assert_eq!(getpgid(leader as usize), leader);
+    assert_eq!(getpgid(0), pgid);
+
+    // 子进程 member 加入 leader 的进程组
+    let member = fork();
+    if member == 0 {
+        setpgid(0, leader as usize);
+        spin();
+    }
+    assert_eq!(setpgid(member as usize, leader as usize), 0);
+    assert_eq!(getpgid(member as usize), leader);
+
+    // 不是组长的子进程可以新建会话，随后不能再被父进程移动
+    let detached = fork();
+    if detached == 0 {
+        let me = getpid();
+        assert_eq!(setsid(), me);
+        assert_eq!(getsid(0), me);
+        assert_eq!(getpgid(0), me);
+        exit(0);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(detached, &mut exit_code), detached);
+    assert_eq!(exit_code, 0);
+
+    // 向整个进程组发送 SIGSTOP，WUNTRACED 报告停止
+    assert_eq!(kill(-leader, SignalNo::SIGSTOP), 0);
+    assert_eq!(wait4(leader, &mut exit_code, WUNTRACED), leader);
+    assert!(wifstopped(exit_code));
+    assert_eq!(wait4(member, &mut exit_code, WUNTRACED), member);
+    assert!(wifstopped(exit_code));
+
+    // 继续运行后终止整个进程组：两个子进程都能被回收
+    assert_eq!(kill(-leader, SignalNo::SIGCONT), 0);
+    assert_eq!(kill(-leader, SignalNo::SIGKILL), 0);
+    for _ in 0..2 {
+        let pid = wait4(-leader, &mut exit_code, 0);
+        assert!(pid == leader || pid == member);
+        assert!(!wifstopped(exit_code));
+    }
+    assert_eq!(wait4(-leader, &mut exit_code, 0), -1);
+    // 本进程不在该组中，没有受到影响
+    assert_eq!(getpgid(0), pgid);
+    println!("ch8 pgroup test passed!");
+    0
+}
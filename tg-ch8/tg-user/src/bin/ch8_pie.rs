@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+/// 内核加载位置无关可执行文件的基址（与内核 `process::PIE_BASE` 一致）
+const PIE_BASE: usize = 0x1000_0000;
+
+fn double(x: usize) -> usize {
+    x * 2
+}
+
+fn square(x: usize) -> usize {
+    x * x
+}
+
+/// 静态数据中的指针：链接时只知道相对地址，加载时由内核重定位
+static OPS: [fn(usize) -> usize; 2] = [double, square];
+static NAMES: [&str; 2] = ["double", "square"];
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let addr = main as *const () as usize;
+    println!("main is at {addr:#x}");
+    assert!(addr >= PIE_BASE);
+    for (op, name) in core::hint::black_box(&OPS).iter().zip(NAMES.iter()) {
+        assert!(*op as usize >= PIE_BASE);
+        println!("{name}(7) = {}", op(7));
+    }
+    assert_eq!(OPS[0](7), 14);
+    assert_eq!(OPS[1](7), 49);
+    assert_eq!(NAMES[1], "square");
+    println!("ch8 pie test passed!");
+    0
+}
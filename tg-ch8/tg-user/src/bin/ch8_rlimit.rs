@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    close, exit, fork, getrlimit, pipe, setrlimit, setsid, sigaction, sigreturn, thread_create,
+    waitpid, waittid, RLimit, SignalAction, SignalNo, RLIMIT_CPU, RLIMIT_NOFILE, RLIMIT_NPROC,
+    RLIMIT_NTHREAD, EAGAIN, EINVAL, EPERM,
+};
+
+/// 已收到 SIGXCPU
+static XCPU: AtomicBool = AtomicBool::new(false);
+
+fn on_xcpu() {
+    XCPU.store(true, Ordering::Release);
+    sigreturn();
+}
+
+fn worker() -> isize {
+    exit(0)
+}
+
+/// 把资源 `resource` 的软限制设为 `cur`，硬限制不变
+fn limit(resource: usize, cur: usize) {
+    let mut rlim = RLimit { cur: 0, max: 0 };
+    assert_eq!(getrlimit(resource, &mut rlim), 0);
+    assert_eq!(setrlimit(resource, &RLimit { cur, max: rlim.max }), 0);
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 硬限制只能降低，软限制不能超过硬限制
+    let mut rlim = RLimit { cur: 0, max: 0 };
+    assert_eq!(getrlimit(RLIMIT_NOFILE, &mut rlim), 0);
+    assert!(rlim.cur <= rlim.max);
+    assert_eq!(setrlimit(RLIMIT_NOFILE, &RLimit { cur: rlim.max + 1, max: rlim.max }), EINVAL);
+    assert_eq!(setrlimit(RLIMIT_NOFILE, &RLimit { cur: rlim.max, max: rlim.max + 1 }), EPERM);
+
+    // 文件描述符：已打开 0、1、2，只能再创建一个管道
+    limit(RLIMIT_NOFILE, 5);
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let mut more = [0usize; 2];
+    assert_eq!(pipe(&mut more), -1);
+    close(fds[0]);
+    close(fds[1]);
+    assert_eq!(pipe(&mut more), 0);
+    close(more[0]);
+    close(more[1]);
+
+    // 进程数：按会话计数，在子进程新建的会话中限制为 2，即只能再有一个未退出的进程
+    let pid = fork();
+    if pid == 0 {
+        assert!(setsid() > 0);
+        limit(RLIMIT_NPROC, 2);
+        let child = fork();
+        if child == 0 {
+            // 孙进程同样计入，不能通过层层 fork 绕过限制
+            assert_eq!(fork(), -1);
+            exit(7);
+        }
+        assert!(child > 0);
+        let mut exit_code = 0;
+        assert_eq!(waitpid(child, &mut exit_code), child);
+        assert_eq!(exit_code, 7);
+        let first = fork();
+        if first == 0 {
+            user_lib::sleep(100);
+            exit(0);
+        }
+        assert_eq!(fork(), -1);
+        assert_eq!(waitpid(first, &mut exit_code), first);
+        exit(0);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 线程数：主线程之外只能再创建一个线程
+    limit(RLIMIT_NTHREAD, 2);
+    let tid = thread_create(worker as *const () as usize, 0);
+    assert!(tid > 0);
+    assert_eq!(thread_create(worker as *const () as usize, 0), EAGAIN);
+    waittid(tid as usize);
+
+    // CPU 时间：超过 1 秒软限制后收到 SIGXCPU
+    let mut action = SignalAction::default();
+    action.handler = on_xcpu as *const () as usize;
+    assert_eq!(sigaction(SignalNo::SIGXCPU, &action, &SignalAction::default()), 0);
+    limit(RLIMIT_CPU, 1);
+    while !XCPU.load(Ordering::Acquire) {
+        core::hint::spin_loop();
+    }
+    println!("ch8 rlimit test passed!");
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, get_time, getrusage, sleep, ClockId, RUsage, TimeSpec, RUSAGE_SELF,
+    RUSAGE_THREAD,
+};
+
+fn cpu_time_ms(clock: ClockId) -> isize {
+    let mut time = TimeSpec::ZERO;
+    assert_eq!(clock_gettime(clock, &mut time as *mut _ as _), 0);
+    (time.tv_sec * 1000 + time.tv_nsec / 1_000_000) as isize
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 忙等 200ms：CPU 时间应随之增长
+    let cpu_start = cpu_time_ms(ClockId::CLOCK_THREAD_CPUTIME_ID);
+    let start = get_time();
+    while get_time() - start < 200 {}
+    let busy = cpu_time_ms(ClockId::CLOCK_THREAD_CPUTIME_ID) - cpu_start;
+    println!("busy 200ms, thread cpu time = {}ms", busy);
+    assert!(busy >= 100, "busy loop not accounted");
+
+    // 睡眠 200ms：不消耗 CPU 时间，但记一次主动让出
+    let mut before = RUsage::ZERO;
+    assert_eq!(getrusage(RUSAGE_THREAD, &mut before), 0);
+    let cpu_start = cpu_time_ms(ClockId::CLOCK_PROCESS_CPUTIME_ID);
+    sleep(200);
+    let slept = cpu_time_ms(ClockId::CLOCK_PROCESS_CPUTIME_ID) - cpu_start;
+    let mut after = RUsage::ZERO;
+    assert_eq!(getrusage(RUSAGE_THREAD, &mut after), 0);
+    println!("sleep 200ms, process cpu time = {}ms", slept);
+    assert!(slept < 100, "sleeping thread was charged");
+    assert!(after.nvcsw > before.nvcsw, "sleep not counted as voluntary switch");
+
+    let mut usage = RUsage::ZERO;
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    assert_eq!(usage.nthreads, 1);
+    println!(
+        "switches = {}, voluntary = {}, involuntary = {}",
+        usage.nswitch, usage.nvcsw, usage.nivcsw
+    );
+    println!("ch8 rusage test passed!");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{exit, get_time, sleep, thread_create, waittid};
+
+/// 各线程的睡眠时长（毫秒）
+const PERIODS: [usize; 4] = [400, 100, 300, 200];
+
+fn sleeper(period_ms: usize) -> isize {
+    let start = get_time();
+    sleep(period_ms);
+    let delta = get_time() - start;
+    println!("slept {}ms, delta = {}ms", period_ms, delta);
+    exit(if delta >= period_ms as isize { 0 } else { 1 })
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let start = get_time();
+    let tids: Vec<isize> = PERIODS
+        .iter()
+        .map(|&period| thread_create(sleeper as *const () as usize, period))
+        .collect();
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0, "woke up too early");
+    }
+    // 线程同时睡眠，总耗时应接近最长的一次而不是全部之和
+    let total = get_time() - start;
+    let sum: usize = PERIODS.iter().sum();
+    println!("total = {}ms", total);
+    assert!(total < sum as isize, "sleeping threads did not overlap");
+    println!("ch8 sleep test passed!");
+    0
+}
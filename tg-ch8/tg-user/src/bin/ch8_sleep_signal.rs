@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// 子进程请求的睡眠时长（毫秒）
+const SLEEP_MS: usize = 2000;
+/// 父进程在发送信号前等待的时长（毫秒）
+const DELAY_MS: usize = 100;
+
+fn on_usr1() {
+    sigreturn();
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 在 fork 之前注册处理函数，子进程继承后不会被默认动作终止
+    let mut new = SignalAction::default();
+    let old = SignalAction::default();
+    new.handler = on_usr1 as *const () as usize;
+    assert!(sigaction(SignalNo::SIGUSR1, &new, &old) >= 0);
+    let pid = fork();
+    if pid == 0 {
+        let start = get_time();
+        let mut rem = TimeSpec::ZERO;
+        let ret = nanosleep(&TimeSpec::from_millsecond(SLEEP_MS), Some(&mut rem));
+        let elapsed = get_time() - start;
+        let rem_ms = rem.tv_sec * 1000 + rem.tv_nsec / 1_000_000;
+        println!("nanosleep = {}, elapsed = {}ms, rem = {}ms", ret, elapsed, rem_ms);
+        // 应被信号提前唤醒：返回 EINTR，并写回非零的剩余时长
+        let ok = ret == EINTR && elapsed < SLEEP_MS as isize && rem_ms > 0 && rem_ms < SLEEP_MS;
+        exit(if ok { 0 } else { 1 });
+    }
+    sleep(DELAY_MS);
+    assert_eq!(kill(pid, SignalNo::SIGUSR1), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0, "nanosleep was not interrupted");
+    println!("ch8 sleep_signal test passed!");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, get_time, getcpu, thread_create, waittid};
+
+/// 各线程见过的逻辑核号集合（按位）
+static SEEN: AtomicUsize = AtomicUsize::new(0);
+/// 忙等线程数，与 QEMU `-smp 4` 的核数相同
+const THREADS: usize = 4;
+/// 每个线程忙等的时长（毫秒），跨越多个时间片
+const BUSY_MS: isize = 300;
+
+fn busy(_arg: usize) -> isize {
+    let start = get_time();
+    while get_time() - start < BUSY_MS {
+        let cpu = getcpu();
+        assert!(cpu >= 0, "getcpu failed: {cpu}");
+        SEEN.fetch_or(1 << cpu, Ordering::Relaxed);
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(busy as *const () as usize, 0);
+        assert!(*tid > 0, "thread_create failed");
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    // 线程在多个核上执行过，说明用户线程确实并行运行
+    let seen = SEEN.load(Ordering::Relaxed);
+    println!("ch8 smp: threads ran on harts {seen:#b}");
+    assert!(seen.count_ones() > 1, "all threads ran on a single hart");
+    println!("ch8 smp test passed!");
+    0
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    args, close, pipe, pipe_read, pipe_write, spawn, spawn_with_fds, waitpid, EBADF, ENOENT, NO_FD,
+};
+
+static STR: &str = "Hello from spawned child!";
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    match args().get(1).copied() {
+        Some("child") => {
+            // 子进程：fd 3 是父进程传下来的管道写端，fd 4 没有继承
+            assert_eq!(close(4), -1);
+            assert_eq!(pipe_write(3, STR.as_bytes()), STR.len() as isize);
+            return 0;
+        }
+        Some("stdio") => {
+            // 子进程：标准输入输出都是管道，print! 写入 fd 1 指向的管道
+            let mut buffer = [0u8; STR.len()];
+            assert_eq!(pipe_read(0, &mut buffer), STR.len() as isize);
+            print!("{}", core::str::from_utf8(&buffer).unwrap());
+            return 0;
+        }
+        _ => {}
+    }
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    // 子进程的 fd 0..=2 继承标准输入输出，fd 3 是管道写端；管道读端不继承
+    let fds = [0, 1, 2, pipe_fd[1], NO_FD];
+    let pid = spawn_with_fds("ch8_spawn", &["ch8_spawn", "child"], &fds);
+    assert!(pid > 0, "spawn failed");
+    close(pipe_fd[1]);
+    let mut buffer = [0u8; STR.len()];
+    let len_read = pipe_read(pipe_fd[0], &mut buffer) as usize;
+    assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 子进程的 fd 0、1 换成管道：读写不再经过控制台
+    let mut input = [0usize; 2];
+    let mut output = [0usize; 2];
+    assert_eq!(pipe(&mut input), 0);
+    assert_eq!(pipe(&mut output), 0);
+    let fds = [input[0], output[1], 2];
+    let pid = spawn_with_fds("ch8_spawn", &["ch8_spawn", "stdio"], &fds);
+    assert!(pid > 0, "spawn failed");
+    close(input[0]);
+    close(output[1]);
+    assert_eq!(pipe_write(input[1], STR.as_bytes()), STR.len() as isize);
+    close(input[1]);
+    let mut buffer = [0u8; STR.len()];
+    assert_eq!(pipe_read(output[0], &mut buffer), STR.len() as isize);
+    assert_eq!(core::str::from_utf8(&buffer).unwrap(), STR);
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(output[0]);
+
+    // 无效的 fd 应导致 spawn 失败
+    assert_eq!(spawn_with_fds("ch8_spawn", &["ch8_spawn"], &[42]), EBADF);
+    // 不存在的程序
+    assert_eq!(spawn("no_such_app", &["no_such_app"]), ENOENT);
+    println!("ch8 spawn test passed!");
+    0
+}
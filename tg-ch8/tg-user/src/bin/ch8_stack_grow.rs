@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::mem::MaybeUninit;
+use user_lib::{
+    close, exit, fork, getrlimit, pipe, pipe_read, pipe_write, setrlimit, waitpid, wifsignaled,
+    wtermsig, RLimit, SignalNo, RLIMIT_STACK,
+};
+
+/// 每层递归在栈上使用的字节数
+const FRAME: usize = 1024;
+/// 交给内核写入的栈缓冲区大小，足以让其底部落在从未访问过的页上
+const UNTOUCHED: usize = 256 * 1024;
+
+/// 让内核通过 read 写入栈上从未访问过的页：缺页由内核代替进程处理，栈同样增长
+#[inline(never)]
+fn kernel_writes_stack() -> bool {
+    let mut buf = MaybeUninit::<[u8; UNTOUCHED]>::uninit();
+    // 数组的起始地址是本栈帧的最低处，进程自己还没有访问过
+    let bottom = unsafe { core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, 4) };
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(pipe_write(fds[1], b"grow"), 4);
+    let n = pipe_read(fds[0], bottom);
+    close(fds[0]);
+    close(fds[1]);
+    n == 4 && bottom == b"grow"
+}
+
+/// 递归 `depth` 层，每层占用约 `FRAME` 字节的栈
+fn recurse(depth: usize) -> usize {
+    let frame = core::hint::black_box([depth as u8; FRAME]);
+    if depth == 0 {
+        return frame[0] as usize;
+    }
+    recurse(depth - 1) + frame[FRAME - 1] as usize
+}
+
+/// `recurse(n)` 的结果：1..=n 各数的低 8 位之和
+fn expected(n: usize) -> usize {
+    (1..=n).map(|i| i % 256).sum()
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let mut limit = RLimit { cur: 0, max: 0 };
+    assert_eq!(getrlimit(RLIMIT_STACK, &mut limit), 0);
+    assert_eq!(limit.cur, 8 << 20);
+    // 硬限制是预留区域的大小，不能提高
+    let raise = RLimit { cur: limit.max + 4096, max: limit.max + 4096 };
+    assert!(setrlimit(RLIMIT_STACK, &raise) < 0);
+
+    // 系统调用的缓冲区位于尚未增长的栈上
+    assert!(kernel_writes_stack(), "kernel could not write to an unfaulted stack page");
+
+    // 超过 512 KiB 的递归：栈在缺页时逐页增长
+    assert_eq!(recurse(2048), expected(2048));
+    // 已经增长过的栈可以再次使用
+    assert_eq!(recurse(2048), expected(2048));
+
+    // 调低软限制后越过限制的访问收到 SIGSEGV（在子进程中进行，不影响本进程）；
+    // 已经映射的页不受影响，所以递归要深过上面的 2 MiB
+    let pid = fork();
+    if pid == 0 {
+        let small = RLimit { cur: 256 * 1024, max: limit.max };
+        assert_eq!(setrlimit(RLIMIT_STACK, &small), 0);
+        assert_eq!(recurse(128), expected(128));
+        exit(recurse(4096) as i32);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert!(wifsignaled(exit_code));
+    assert_eq!(wtermsig(exit_code), SignalNo::SIGSEGV as i32);
+
+    println!("ch8 stack grow test passed!");
+    0
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{exit, get_time, set_priority, thread_create, waittid};
+
+fn spin_delay() {
+    let mut j = true;
+    for _ in 0..10 {
+        j = !j;
+    }
+}
+
+const MAX_TIME: isize = 4000;
+
+fn count_during(prio: usize) -> isize {
+    set_priority(prio as isize);
+    let start_time = get_time();
+    let mut acc = 0;
+    loop {
+        spin_delay();
+        acc += 1;
+        if acc % 400 == 0 {
+            let time = get_time() - start_time;
+            if time > MAX_TIME {
+                exit(acc as i32);
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 主线程创建完工作线程后阻塞在 waittid 中，不参与竞争
+    let prios = [5usize, 6, 7, 8, 9, 10];
+    let tids: Vec<isize> = prios
+        .iter()
+        .map(|&prio| thread_create(count_during as *const () as usize, prio))
+        .collect();
+    let mut ratios = Vec::new();
+    for (tid, prio) in tids.iter().zip(prios.iter()) {
+        let count = waittid(*tid as usize);
+        assert!(count > 0, "waittid failed: {count}");
+        let ratio = count / *prio as isize;
+        println!("priority = {}, exitcode = {}, ratio = {}", prio, count, ratio);
+        ratios.push(ratio);
+    }
+    // 计数与优先级成正比：各线程的 count / prio 相差不超过 50%
+    let max = *ratios.iter().max().unwrap();
+    let min = *ratios.iter().min().unwrap();
+    assert!(max * 2 <= min * 3, "ratios differ too much: {min} .. {max}");
+    println!("ch8 stride test passed!");
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, gettid, sleep, thread_create, thread_detach, waitpid, waittid, EDEADLK, EINVAL,
+    ESRCH,
+};
+
+/// 已结束的分离线程数
+static FINISHED: AtomicUsize = AtomicUsize::new(0);
+/// 分离线程的数量：超过默认线程数限制，分离线程不被回收就会创建失败
+const DETACHED: usize = 400;
+
+fn slow(arg: usize) -> isize {
+    sleep(50);
+    exit(arg as i32)
+}
+
+fn detached(_arg: usize) -> isize {
+    FINISHED.fetch_add(1, Ordering::Relaxed);
+    exit(0)
+}
+
+fn self_detach(_arg: usize) -> isize {
+    assert_eq!(thread_detach(0), 0);
+    FINISHED.fetch_add(1, Ordering::Relaxed);
+    exit(0)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // waittid 阻塞到线程退出，而不是立即返回
+    let tid = thread_create(slow as *const () as usize, 42);
+    assert_eq!(waittid(tid as usize), 42);
+    // 已回收的线程不能再等待，也不能等待自己
+    assert_eq!(waittid(tid as usize), ESRCH);
+    assert_eq!(waittid(gettid() as usize), EDEADLK);
+
+    // 分离后不能等待
+    let tid = thread_create(slow as *const () as usize, 0);
+    assert_eq!(thread_detach(tid as usize), 0);
+    assert_eq!(waittid(tid as usize), EINVAL);
+    assert_eq!(thread_detach(tid as usize), EINVAL);
+
+    // 分离的线程退出后自动回收，不会占用线程数限制
+    for i in 0..DETACHED {
+        let entry = if i % 2 == 0 { detached } else { self_detach };
+        let tid = thread_create(entry as *const () as usize, 0);
+        assert!(tid > 0, "thread_create failed at {i}");
+        if i % 2 == 0 {
+            thread_detach(tid as usize);
+        }
+        if i % 64 == 63 {
+            sleep(10);
+        }
+    }
+    while FINISHED.load(Ordering::Relaxed) < DETACHED {
+        sleep(10);
+    }
+
+    // 不能等待其他进程的线程
+    let main_tid = gettid();
+    let pid = fork();
+    if pid == 0 {
+        exit(waittid(main_tid as usize) as i32);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code as isize, ESRCH);
+    println!("ch8 thread detach test passed!");
+    0
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    args, execve, exit, fork, getrusage, sleep, thread_create, waitpid, waittid, RUsage,
+    RUSAGE_SELF,
+};
+
+/// fork 出的子进程的退出码
+const FORK_EXIT: i32 = 11;
+/// exec 后新程序的退出码
+const EXEC_EXIT: i32 = 22;
+
+/// exec 失败时通知自旋线程结束
+static DONE: AtomicBool = AtomicBool::new(false);
+
+/// 当前进程的线程数
+fn nthreads() -> usize {
+    let mut usage = RUsage::ZERO;
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    usage.nthreads
+}
+
+/// 睡眠一段时间，保证 fork/exec 时进程里还有其他线程
+fn sleeper(period_ms: usize) -> isize {
+    sleep(period_ms);
+    exit(0)
+}
+
+/// 一直占用 CPU，直到被 exec 结束
+fn spinner(_: usize) -> isize {
+    while !DONE.load(Ordering::Relaxed) {}
+    exit(0)
+}
+
+/// 在非主线程中 fork：子进程只含调用线程，并从这里继续执行
+fn forker(_: usize) -> isize {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(nthreads(), 1, "fork copied other threads");
+        exit(FORK_EXIT);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, FORK_EXIT);
+    exit(0)
+}
+
+/// 在非主线程中 exec：成功后不会返回
+fn execer(_: usize) -> isize {
+    execve("ch8_thread_fork", &["ch8_thread_fork", "exec"], &[]);
+    exit(-1)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    if args().len() > 1 {
+        // exec 之后：其他线程都已结束
+        assert_eq!(nthreads(), 1, "exec left other threads running");
+        exit(EXEC_EXIT);
+    }
+
+    let sleeper_tid = thread_create(sleeper as *const () as usize, 200);
+    let forker_tid = thread_create(forker as *const () as usize, 0);
+    assert_eq!(waittid(forker_tid as usize), 0);
+    assert_eq!(waittid(sleeper_tid as usize), 0);
+    println!("fork from a thread: ok");
+
+    let pid = fork();
+    if pid == 0 {
+        thread_create(sleeper as *const () as usize, 10_000);
+        thread_create(spinner as *const () as usize, 0);
+        let execer_tid = thread_create(execer as *const () as usize, 0);
+        // exec 成功时本线程也会被结束，走到这里说明 exec 失败
+        waittid(execer_tid as usize);
+        DONE.store(true, Ordering::Relaxed);
+        exit(1);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, EXEC_EXIT);
+    println!("exec from a thread: ok");
+    println!("ch8 thread fork test passed!");
+    0
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, thread_create, thread_create_with_stack, waitpid, waittid, wifsignaled, wtermsig,
+    SignalNo,
+};
+
+/// 大栈线程的栈大小
+const BIG_STACK: usize = 64 * 1024;
+/// 创建并回收大栈线程的次数：总量超过默认 `RLIMIT_AS`，栈不释放就会失败
+const ROUNDS: usize = 1200;
+
+/// 在栈上使用 32 KiB
+fn big_frame(arg: usize) -> isize {
+    let mut buf = [0u8; 32 * 1024];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (i + arg) as u8;
+    }
+    let sum: usize = buf.iter().map(|&b| b as usize).sum();
+    exit(core::hint::black_box(sum) as i32 & 0x7f)
+}
+
+fn small(arg: usize) -> isize {
+    exit(arg as i32)
+}
+
+/// 无限递归，最终访问保护页
+#[allow(unconditional_recursion)]
+fn recurse(depth: usize) -> usize {
+    let frame = core::hint::black_box([depth; 64]);
+    recurse(depth + 1) + frame[0]
+}
+
+fn overflow(_arg: usize) -> isize {
+    exit(recurse(0) as i32)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 指定大小的栈可以容纳较大的栈帧
+    let tid = thread_create_with_stack(big_frame as *const () as usize, 1, BIG_STACK);
+    assert!(tid > 0);
+    assert!(waittid(tid as usize) >= 0);
+
+    // 回收后栈被释放、区间被复用
+    for i in 0..ROUNDS {
+        let tid = thread_create_with_stack(small as *const () as usize, i % 100, BIG_STACK);
+        assert!(tid > 0, "thread_create failed in round {i}");
+        assert_eq!(waittid(tid as usize), (i % 100) as isize);
+    }
+    let tid = thread_create(small as *const () as usize, 5);
+    assert_eq!(waittid(tid as usize), 5);
+
+    // 栈溢出访问保护页，整个进程被 SIGSEGV 终止（在子进程中进行，不影响本进程）
+    let pid = fork();
+    if pid == 0 {
+        let tid = thread_create(overflow as *const () as usize, 0);
+        waittid(tid as usize);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert!(wifsignaled(exit_code));
+    assert_eq!(wtermsig(exit_code), SignalNo::SIGSEGV as i32);
+    println!("ch8 thread stack test passed!");
+    0
+}
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{errno, exit, set_errno, sleep, thread_create, waittid, TlsKey};
+
+/// 线程数
+const THREADS: usize = 4;
+
+static mut KEY: Option<TlsKey> = None;
+
+fn key() -> TlsKey {
+    unsafe { KEY.unwrap() }
+}
+
+fn worker(arg: usize) -> isize {
+    // 新线程的 TLS 块从模板初始化，槽位为 0
+    assert_eq!(key().get(), 0);
+    assert_eq!(errno(), 0);
+    key().set(arg * 100);
+    set_errno(arg as isize);
+    // 让其他线程有机会改写各自的值
+    sleep(10);
+    assert_eq!(key().get(), arg * 100);
+    assert_eq!(errno(), arg as isize);
+    exit(arg as i32)
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    unsafe { KEY = TlsKey::new() };
+    key().set(7);
+    set_errno(-1);
+    let tids: [isize; THREADS] =
+        core::array::from_fn(|i| thread_create(worker as *const () as usize, i + 1));
+    for (i, &tid) in tids.iter().enumerate() {
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), (i + 1) as isize);
+    }
+    // 其他线程没有影响主线程的值
+    assert_eq!(key().get(), 7);
+    assert_eq!(errno(), -1);
+    println!("ch8 tls test passed!");
+    0
+}
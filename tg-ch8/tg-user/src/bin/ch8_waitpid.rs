@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, sleep, wait, wait4, waitpid, WNOHANG};
+
+/// 子进程的睡眠时长（毫秒）
+const PERIOD: usize = 200;
+/// 子进程的退出码
+const EXIT_CODE: i32 = 7;
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let start = get_time();
+    let pid = fork();
+    if pid == 0 {
+        sleep(PERIOD);
+        exit(EXIT_CODE);
+    }
+    let mut exit_code: i32 = 0;
+    // 子进程仍在睡眠：WNOHANG 立即返回 0
+    assert_eq!(wait4(pid, &mut exit_code, WNOHANG), 0);
+    // 阻塞直到子进程退出
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, EXIT_CODE);
+    let delta = get_time() - start;
+    println!("waited {}ms", delta);
+    assert!(delta >= PERIOD as isize, "wait returned too early");
+    // 没有子进程了
+    assert_eq!(wait(&mut exit_code), -1);
+    assert_eq!(wait4(-1, &mut exit_code, WNOHANG), -1);
+    println!("ch8 waitpid test passed!");
+    0
+}
@@ -11,6 +11,7 @@ extern "C" fn main() -> i32 {
         let target = "user_shell";
         exec(target);
     } else {
+        // 回收 user_shell 以及过继来的孤儿进程，没有子进程时退出（随后内核关机）
         loop {
             let mut exit_code: i32 = 0;
             let pid = wait(&mut exit_code);
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use user_lib::{get_time, proc_usage, sleep, RUsage, TimeSpec};
+
+/// 刷新间隔（毫秒）
+const INTERVAL: usize = 1000;
+/// 刷新次数
+const ROUNDS: usize = 10;
+
+fn to_ms(t: &TimeSpec) -> usize {
+    t.tv_sec * 1000 + t.tv_nsec / 1_000_000
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    // 上一轮采样时各进程的 CPU 时间（毫秒）
+    let mut last: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut last_time = get_time() as usize;
+    for _ in 0..ROUNDS {
+        sleep(INTERVAL);
+        let now = get_time() as usize;
+        let elapsed = (now - last_time).max(1);
+        last_time = now;
+
+        println!("\x1b[2J\x1b[H  PID  THR   %CPU   USER(ms)    SYS(ms)   SWITCH   VOL  INVOL");
+        let mut current = BTreeMap::new();
+        let mut usage = RUsage::ZERO;
+        let mut pid = 0;
+        loop {
+            let found = proc_usage(pid, &mut usage);
+            if found < 0 {
+                break;
+            }
+            let found = found as usize;
+            let (user, sys) = (to_ms(&usage.utime), to_ms(&usage.stime));
+            let delta = (user + sys).saturating_sub(last.get(&found).copied().unwrap_or(0));
+            println!(
+                "{:>5} {:>4} {:>6} {:>10} {:>10} {:>8} {:>5} {:>6}",
+                found,
+                usage.nthreads,
+                delta * 100 / elapsed,
+                user,
+                sys,
+                usage.nswitch,
+                usage.nvcsw,
+                usage.nivcsw,
+            );
+            current.insert(found, user + sys);
+            pid = found + 1;
+        }
+        last = current;
+    }
+    0
+}
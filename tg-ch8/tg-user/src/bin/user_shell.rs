@@ -12,12 +12,38 @@ const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
 
-use alloc::string::String;
-use user_lib::{exec, fork, getchar, waitpid};
+use alloc::{string::String, vec::Vec};
+use user_lib::{
+    execve, exit, fork, getchar, getpgid, kill, setpgid, sigaction, sigreturn, tcsetpgrp, wait4,
+    wifstopped, SignalAction, SignalNo, WNOHANG, WUNTRACED,
+};
+
+/// 作业：每个作业是一个独立的进程组，组长即作业的第一个进程
+struct Job {
+    /// 作业编号（从 1 开始）
+    id: usize,
+    /// 进程组 ID
+    pgid: usize,
+    /// 命令行
+    cmd: String,
+    /// 是否已被停止
+    stopped: bool,
+}
+
+/// shell 收到 Ctrl-C 时什么也不做（前台作业会收到同一个信号）
+fn ignore_signal() {
+    sigreturn();
+}
 
 #[no_mangle]
 pub extern "C" fn main() -> i32 {
     println!("Rust user shell");
+    // shell 自己不响应 Ctrl-C，只让前台作业终止
+    let mut action = SignalAction::default();
+    action.handler = ignore_signal as *const () as usize;
+    sigaction(SignalNo::SIGINT, &action, &SignalAction::default());
+
+    let mut jobs: Vec<Job> = Vec::new();
     let mut line: String = String::new(); // 记录着当前输入的命令
     print!(">> ");
     loop {
@@ -26,23 +52,9 @@ pub extern "C" fn main() -> i32 {
             LF | CR => {
                 // 换行
                 println!();
-                if !line.is_empty() {
-                    let pid = fork();
-                    if pid == 0 {
-                        // child process
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as isize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
-                    }
-                    line.clear();
-                }
+                run_line(&line, &mut jobs);
+                reap_background(&mut jobs);
+                line.clear();
                 print!(">> ");
             }
             BS | DL => {
@@ -61,3 +73,109 @@ pub extern "C" fn main() -> i32 {
         }
     }
 }
+
+/// 执行一行命令：内建命令 `jobs`、`fg [n]`、`bg [n]`，或启动新作业（以 `&` 结尾时在后台运行）
+fn run_line(line: &str, jobs: &mut Vec<Job>) {
+    // 按空白切分命令行，第一个参数是程序名
+    let mut args: Vec<&str> = line.split_whitespace().collect();
+    let background = args.last() == Some(&"&");
+    if background {
+        args.pop();
+    }
+    let Some(&cmd) = args.first() else {
+        return;
+    };
+    match cmd {
+        "jobs" => {
+            for job in jobs.iter() {
+                let state = if job.stopped { "Stopped" } else { "Running" };
+                println!("[{}] {} {}", job.id, state, job.cmd);
+            }
+        }
+        "fg" | "bg" => {
+            let Some(index) = find_job(jobs, args.get(1)) else {
+                println!("{cmd}: no such job");
+                return;
+            };
+            let job = &mut jobs[index];
+            println!("{}", job.cmd);
+            job.stopped = false;
+            if cmd == "fg" {
+                tcsetpgrp(job.pgid);
+                kill(-(job.pgid as isize), SignalNo::SIGCONT);
+                wait_foreground(jobs, index);
+            } else {
+                kill(-(job.pgid as isize), SignalNo::SIGCONT);
+            }
+        }
+        _ => {
+            let pid = fork();
+            if pid == 0 {
+                // child process：自成一个进程组，前台作业同时接管控制台
+                setpgid(0, 0);
+                if !background {
+                    tcsetpgrp(getpgid(0) as usize);
+                }
+                if execve(args[0], &args, &[]) < 0 {
+                    println!("Error when executing!");
+                    exit(-4);
+                }
+                unreachable!();
+            }
+            // 父子进程都设置进程组，避免任何一方先运行时出现竞争
+            let pgid = pid as usize;
+            setpgid(pgid, pgid);
+            let id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+            jobs.push(Job { id, pgid, cmd: args.join(" "), stopped: false });
+            if background {
+                println!("[{id}] {pid}");
+            } else {
+                tcsetpgrp(pgid);
+                wait_foreground(jobs, jobs.len() - 1);
+            }
+        }
+    }
+}
+
+/// 按编号查找作业，未给出编号时取最近的作业
+fn find_job(jobs: &[Job], id: Option<&&str>) -> Option<usize> {
+    match id {
+        Some(id) => {
+            let id: usize = id.trim_start_matches('%').parse().ok()?;
+            jobs.iter().position(|job| job.id == id)
+        }
+        None => jobs.len().checked_sub(1),
+    }
+}
+
+/// 等待前台作业退出或停止，随后收回控制台
+fn wait_foreground(jobs: &mut Vec<Job>, index: usize) {
+    let pgid = jobs[index].pgid;
+    let mut exit_code: i32 = 0;
+    let pid = wait4(pgid as isize, &mut exit_code, WUNTRACED);
+    tcsetpgrp(getpgid(0) as usize);
+    if pid < 0 {
+        jobs.remove(index);
+    } else if wifstopped(exit_code) {
+        let job = &mut jobs[index];
+        job.stopped = true;
+        println!("\n[{}] Stopped {}", job.id, job.cmd);
+    } else {
+        jobs.remove(index);
+        println!("Shell: Process {} exited with code {}", pid, exit_code);
+    }
+}
+
+/// 回收已经结束的后台作业并报告
+fn reap_background(jobs: &mut Vec<Job>) {
+    let mut i = 0;
+    while i < jobs.len() {
+        let mut exit_code: i32 = 0;
+        if wait4(jobs[i].pgid as isize, &mut exit_code, WNOHANG) > 0 {
+            let job = jobs.remove(i);
+            println!("[{}] Done {}", job.id, job.cmd);
+        } else {
+            i += 1;
+        }
+    }
+}
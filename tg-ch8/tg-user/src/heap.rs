@@ -5,6 +5,7 @@ use core::{
     ptr::NonNull,
 };
 use customizable_buddy::{BuddyAllocator, LinkedListBuddy, UsizeBuddy};
+use tg_syscall::sbrk;
 
 /// 初始化全局分配器和内核堆分配器。
 struct StaticCell<T> {
@@ -27,7 +28,7 @@ impl<T> StaticCell<T> {
 }
 
 pub fn init() {
-    // 托管空间 4 MiB
+    // 初始托管空间 4 MiB，不够时通过 sbrk 扩展（见 `grow`）
     const MEMORY_SIZE: usize = 4 << 20;
     static MEMORY: StaticCell<[u8; MEMORY_SIZE]> = StaticCell::new([0u8; MEMORY_SIZE]);
     unsafe {
@@ -50,6 +51,28 @@ fn heap_mut() -> &'static mut MutAllocator<32> {
     unsafe { &mut *HEAP.get() }
 }
 
+/// 每次 sbrk 至少扩展的字节数
+const MIN_GROW: usize = 1 << 20;
+
+/// 通过 sbrk 扩展堆，使之能满足 `layout`，内核拒绝时返回 `false`
+///
+/// 伙伴分配器的块按大小对齐，扩展两倍于所需块的空间，保证其中有一个对齐的完整块。
+fn grow(layout: Layout) -> bool {
+    let block = layout.size().max(layout.align()).next_power_of_two();
+    let Some(size) = block.checked_mul(2).map(|size| size.max(MIN_GROW)) else {
+        return false;
+    };
+    let Ok(increment) = i32::try_from(size) else {
+        return false;
+    };
+    let base = sbrk(increment);
+    if base == -1 {
+        return false;
+    }
+    unsafe { heap_mut().transfer(NonNull::new_unchecked(base as usize as *mut u8), size) };
+    true
+}
+
 struct Global;
 
 #[global_allocator]
@@ -59,10 +82,14 @@ unsafe impl GlobalAlloc for Global {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         if let Ok((ptr, _)) = heap_mut().allocate_layout::<u8>(layout) {
-            ptr.as_ptr()
-        } else {
-            handle_alloc_error(layout)
+            return ptr.as_ptr();
+        }
+        if grow(layout) {
+            if let Ok((ptr, _)) = heap_mut().allocate_layout::<u8>(layout) {
+                return ptr.as_ptr();
+            }
         }
+        handle_alloc_error(layout)
     }
 
     #[inline]
@@ -1,30 +1,64 @@
 #![no_std]
 
 mod heap;
+pub mod mman;
+mod tls;
 
 extern crate alloc;
 
+use alloc::{string::String, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use tg_console::log;
 
 pub use tg_console::{print, println};
+pub use tls::{errno, set_errno, TlsKey, TLS_SLOTS};
 pub use tg_syscall::*;
+use tg_syscall::native::*;
 
+/// 内核在 a0、a1 中传入 argc、argv（栈上同时按 System V 布局保存了一份）
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: *const *const u8) -> ! {
     tg_console::init_console(&Console);
     tg_console::set_log_level(option_env!("LOG"));
     heap::init();
+    ARGC.store(argc, Ordering::Relaxed);
+    ARGV.store(argv as usize, Ordering::Relaxed);
 
     extern "C" {
-        fn main() -> i32;
+        fn main(argc: usize, argv: *const *const u8) -> i32;
     }
 
-    // SAFETY: main 函数由用户程序提供，链接器保证其存在且符合 C ABI
-    exit(unsafe { main() });
+    // SAFETY: main 函数由用户程序提供，链接器保证其存在且符合 C ABI；
+    // 不关心参数的程序可以声明为 `main() -> i32`，多余的参数寄存器会被忽略
+    exit(unsafe { main(argc, argv) });
     unreachable!()
 }
 
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+static ARGV: AtomicUsize = AtomicUsize::new(0);
+
+/// 获取命令行参数（第 0 个为程序名）
+pub fn args() -> Vec<&'static str> {
+    let argv = ARGV.load(Ordering::Relaxed) as *const *const u8;
+    (0..ARGC.load(Ordering::Relaxed))
+        .map(|i| unsafe { cstr(*argv.add(i)) })
+        .collect()
+}
+
+/// 把以 NUL 结尾的字符串转换为 `&str`
+///
+/// # Safety
+///
+/// `ptr` 必须指向一个在程序生命周期内有效、以 NUL 结尾的 UTF-8 字符串。
+unsafe fn cstr(ptr: *const u8) -> &'static str {
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len))
+}
+
 #[panic_handler]
 fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     let err = panic_info.message();
@@ -57,20 +91,301 @@ impl tg_console::Console for Console {
     }
 }
 
+/// 内核按 C 约定读取字符串：以 NUL 结尾
+fn to_c(s: &str) -> String {
+    let mut c = String::from(s);
+    c.push('\0');
+    c
+}
+
+/// 以 NULL 结尾的字符串指针数组（`strings` 必须已经以 NUL 结尾）
+fn to_c_array(strings: &[String]) -> Vec<usize> {
+    strings.iter().map(|s| s.as_ptr() as usize).chain([0]).collect()
+}
+
+/// execve 系统调用：以参数 `argv`、环境变量 `envp` 执行 `path` 指定的程序
+///
+/// 成功时不返回，失败返回负的错误码（`ENOENT`、`ENOEXEC`、`E2BIG` 等）。
+pub fn execve(path: &str, argv: &[&str], envp: &[&str]) -> isize {
+    let path = to_c(path);
+    let argv: Vec<String> = argv.iter().map(|s| to_c(s)).collect();
+    let envp: Vec<String> = envp.iter().map(|s| to_c(s)).collect();
+    let (argv_ptrs, envp_ptrs) = (to_c_array(&argv), to_c_array(&envp));
+    unsafe {
+        syscall3(
+            SyscallId(221),
+            path.as_ptr() as usize,
+            argv_ptrs.as_ptr() as usize,
+            envp_ptrs.as_ptr() as usize,
+        )
+    }
+}
+
+/// spawn 的 fd 映射中表示"不继承"的值
+pub const NO_FD: usize = usize::MAX;
+
+/// spawn 系统调用：以参数 `argv` 创建执行 `path` 的子进程，子进程继承 fd 0、1、2
+///
+/// 成功返回子进程 PID，失败返回负的错误码（与 `execve` 相同，另有 `EAGAIN`、`EBADF`）。
+pub fn spawn(path: &str, argv: &[&str]) -> isize {
+    spawn_with_fds(path, argv, &[0, 1, 2])
+}
+
+/// 同 `spawn`，但子进程的 fd `i` 是当前进程 fd `fds[i]` 的副本（`NO_FD` 表示留空）
+pub fn spawn_with_fds(path: &str, argv: &[&str], fds: &[usize]) -> isize {
+    let path = to_c(path);
+    let argv: Vec<String> = argv.iter().map(|s| to_c(s)).collect();
+    let argv_ptrs = to_c_array(&argv);
+    unsafe {
+        syscall4(
+            SyscallId(400),
+            path.as_ptr() as usize,
+            argv_ptrs.as_ptr() as usize,
+            fds.as_ptr() as usize,
+            fds.len(),
+        )
+    }
+}
+
+/// 执行 `path` 指定的程序，程序名作为唯一的参数
+pub fn exec(path: &str) -> isize {
+    execve(path, &[path], &[])
+}
+
+/// waitpid 的 `options`：子进程尚未退出时立即返回 0
+pub const WNOHANG: usize = 1;
+/// waitpid 的 `options`：同时报告已停止的子进程
+pub const WUNTRACED: usize = 2;
+/// 子进程停止时 wait4 写回的状态中置位的标志，低位为停止信号编号
+pub const WSTOPPED: i32 = 1 << 30;
+
+/// wait4 写回的状态是否表示子进程已停止（而非退出）
+pub fn wifstopped(status: i32) -> bool {
+    status & WSTOPPED != 0
+}
+
+/// 子进程被信号终止时 wait4 写回的状态中置位的标志，低位为终止它的信号编号
+pub const WSIGNALED: i32 = 1 << 29;
+
+/// wait4 写回的状态是否表示子进程被信号终止
+pub fn wifsignaled(status: i32) -> bool {
+    status & WSIGNALED != 0
+}
+
+/// 子进程被信号终止并写出了核心转储 `core.<pid>` 时，wait4 写回的状态中与 `WSIGNALED` 同时置位的标志
+pub const WCOREFLAG: i32 = 0x80;
+
+/// 终止或停止子进程的信号编号（`wifsignaled` 或 `wifstopped` 成立时有意义）
+pub fn wtermsig(status: i32) -> i32 {
+    status & 0x7f
+}
+
+/// 被信号终止的子进程是否写出了核心转储
+pub fn wcoredump(status: i32) -> bool {
+    wifsignaled(status) && status & WCOREFLAG != 0
+}
+
+/// 阻塞等待任意子进程退出，返回其 PID；没有子进程时返回 -1
+pub fn wait(exit_code: &mut i32) -> isize {
+    wait4(-1, exit_code, 0)
+}
+
+/// 阻塞等待 `pid` 指定的子进程退出，返回其 PID；没有该子进程时返回 -1
+pub fn waitpid(pid: isize, exit_code: &mut i32) -> isize {
+    wait4(pid, exit_code, 0)
+}
+
+/// wait4 系统调用：`pid` 为 -1 时等待任意子进程，0 时等待同组子进程，小于 -1 时等待进程组 `-pid` 中的子进程；
+/// `options` 可包含 `WNOHANG`、`WUNTRACED`
+pub fn wait4(pid: isize, exit_code: &mut i32, options: usize) -> isize {
+    unsafe { syscall3(SyscallId(260), pid as usize, exit_code as *mut _ as usize, options) }
+}
+
+/// 父进程 PID：父进程退出后为 initproc 的 PID，initproc 本身为 0
+pub fn getppid() -> isize {
+    unsafe { syscall0(SyscallId(173)) }
+}
+
+/// 把进程 `pid`（0 为自身，或自己的子进程）移入进程组 `pgid`（0 表示以 `pid` 为组长新建）
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    unsafe { syscall2(SyscallId(154), pid, pgid) }
+}
+
+/// 进程 `pid`（0 为自身）的进程组 ID
+pub fn getpgid(pid: usize) -> isize {
+    unsafe { syscall1(SyscallId(155), pid) }
+}
+
+/// 进程 `pid`（0 为自身）的会话 ID
+pub fn getsid(pid: usize) -> isize {
+    unsafe { syscall1(SyscallId(156), pid) }
+}
+
+/// 新建会话并成为会话首进程，返回会话 ID；已是进程组组长时返回 `EPERM`
+pub fn setsid() -> isize {
+    unsafe { syscall0(SyscallId(157)) }
+}
+
+/// 控制台的前台进程组
+pub fn tcgetpgrp() -> isize {
+    unsafe { syscall0(SyscallId(2001)) }
+}
+
+/// 把进程组 `pgid` 设为控制台的前台进程组，Ctrl-C / Ctrl-Z 只发给前台进程组
+pub fn tcsetpgrp(pgid: usize) -> isize {
+    unsafe { syscall1(SyscallId(2002), pgid) }
+}
+
+/// 创建线程，栈大小为 `stack_size` 字节（向上取整到页）；`thread_create` 使用 8 KiB 的默认栈
+///
+/// 栈下方有一个不映射的保护页，栈溢出时进程收到 SIGSEGV。
+pub fn thread_create_with_stack(entry: usize, arg: usize, stack_size: usize) -> isize {
+    unsafe { syscall3(SyscallId(2003), entry, arg, stack_size) }
+}
+
+// 本章新增的系统调用出错时返回取负的 Linux 错误码；前几章已有的系统调用仍返回 -1
+
+/// 不允许的操作，如已是组长时 setsid、setrlimit 提高硬限制
+pub const EPERM: isize = -1;
+/// execve / spawn 的程序不存在
+pub const ENOENT: isize = -2;
+/// 没有这个进程或线程（线程可能已被回收）
+pub const ESRCH: isize = -3;
+/// nanosleep 被信号打断
+pub const EINTR: isize = -4;
+/// execve / spawn 的参数过长
+pub const E2BIG: isize = -7;
+/// execve / spawn 的程序不是可加载的 ELF
+pub const ENOEXEC: isize = -8;
+/// `fd` 不是打开的文件（mmap 还要求是普通文件）
+pub const EBADF: isize = -9;
+/// 超过 `RLIMIT_NPROC`、`RLIMIT_NTHREAD`
+pub const EAGAIN: isize = -11;
+/// 内存不足、超过 `RLIMIT_AS`，或区间不在 mmap 区域内
+pub const ENOMEM: isize = -12;
+/// 文件的打开方式不允许所要求的映射
+pub const EACCES: isize = -13;
+/// 传入的地址不可访问
+pub const EFAULT: isize = -14;
+/// `MAP_FIXED_NOREPLACE` 的区间已被占用
+pub const EEXIST: isize = -17;
+/// 参数无效，如 waittid 的线程已分离
+pub const EINVAL: isize = -22;
+/// 调用者不在控制台的会话中
+pub const ENOTTY: isize = -25;
+/// waittid 等待自己
+pub const EDEADLK: isize = -35;
+
+/// 分离线程 `tid`（0 表示当前线程）：它退出后由内核自动回收，不能再被 `waittid` 等待
+///
+/// `waittid` 会阻塞到目标线程退出，失败时返回 `ESRCH`、`EINVAL` 或 `EDEADLK`。
+pub fn thread_detach(tid: usize) -> isize {
+    unsafe { syscall1(SyscallId(2004), tid) }
+}
+
+/// 睡眠 `period_ms` 毫秒，期间不占用 CPU
 pub fn sleep(period_ms: usize) {
-    let mut time: TimeSpec = TimeSpec::ZERO;
-    clock_gettime(ClockId::CLOCK_MONOTONIC, &mut time as *mut _ as _);
-    let time = time + TimeSpec::from_millsecond(period_ms);
-    loop {
-        let mut now: TimeSpec = TimeSpec::ZERO;
-        clock_gettime(ClockId::CLOCK_MONOTONIC, &mut now as *mut _ as _);
-        if now > time {
-            break;
-        }
-        sched_yield();
+    nanosleep(&TimeSpec::from_millsecond(period_ms), None);
+}
+
+/// nanosleep 系统调用：阻塞当前线程 `req` 指定的时长，成功返回 0
+///
+/// 被信号提前唤醒时返回 `EINTR`，并把剩余时长写入 `rem`（若提供）。
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    let rem = rem.map_or(0, |rem| rem as *mut _ as usize);
+    unsafe { syscall2(SyscallId(101), req as *const _ as usize, rem) }
+}
+
+/// 资源编号：CPU 时间（秒），超过软限制收到 SIGXCPU，超过硬限制被终止
+pub const RLIMIT_CPU: usize = 0;
+/// 资源编号：主线程栈的大小（字节），栈按需增长到这里为止，越过时收到 SIGSEGV
+pub const RLIMIT_STACK: usize = 3;
+/// 资源编号：核心转储文件的大小（字节），为 0 时不转储
+pub const RLIMIT_CORE: usize = 4;
+/// 资源编号：所在会话中未退出的进程数
+pub const RLIMIT_NPROC: usize = 6;
+/// 资源编号：打开的文件描述符数
+pub const RLIMIT_NOFILE: usize = 7;
+/// 资源编号：映射的用户内存（字节）
+pub const RLIMIT_AS: usize = 9;
+/// 资源编号：进程的线程数
+pub const RLIMIT_NTHREAD: usize = 16;
+/// 不限制
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+/// 资源限制（与内核 `rlimit::RLimit` 布局一致）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RLimit {
+    /// 软限制
+    pub cur: usize,
+    /// 硬限制
+    pub max: usize,
+}
+
+/// 读取当前进程对资源 `resource` 的限制
+pub fn getrlimit(resource: usize, limit: &mut RLimit) -> isize {
+    unsafe { syscall2(SyscallId(163), resource, limit as *mut _ as usize) }
+}
+
+/// 设置当前进程对资源 `resource` 的限制；软限制不能超过硬限制，硬限制只能降低
+pub fn setrlimit(resource: usize, limit: &RLimit) -> isize {
+    unsafe { syscall2(SyscallId(164), resource, limit as *const _ as usize) }
+}
+
+/// getrusage 的 `who`：当前进程
+pub const RUSAGE_SELF: isize = 0;
+/// getrusage 的 `who`：当前线程
+pub const RUSAGE_THREAD: isize = 1;
+
+/// 资源使用统计（与内核 `syscall_ext::RUsage` 布局一致）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RUsage {
+    /// 用户态时间
+    pub utime: TimeSpec,
+    /// 内核态时间
+    pub stime: TimeSpec,
+    /// 被调度上 CPU 的次数
+    pub nswitch: usize,
+    /// 主动让出 CPU 的次数（阻塞或 sched_yield）
+    pub nvcsw: usize,
+    /// 被动让出 CPU 的次数（被抢占）
+    pub nivcsw: usize,
+    /// 线程数
+    pub nthreads: usize,
+}
+
+impl RUsage {
+    pub const ZERO: Self = Self {
+        utime: TimeSpec::ZERO,
+        stime: TimeSpec::ZERO,
+        nswitch: 0,
+        nvcsw: 0,
+        nivcsw: 0,
+        nthreads: 0,
+    };
+}
+
+/// getrusage 系统调用：查询当前进程或当前线程的资源使用
+pub fn getrusage(who: isize, usage: &mut RUsage) -> isize {
+    unsafe { syscall2(SyscallId(165), who as usize, usage as *mut _ as usize) }
+}
+
+/// getcpu 系统调用：调用线程当前所在的逻辑核号，失败时返回负数
+pub fn getcpu() -> isize {
+    let mut cpu = 0u32;
+    match unsafe { syscall2(SyscallId(168), &mut cpu as *mut _ as usize, 0) } {
+        0 => cpu as isize,
+        err => err,
     }
 }
 
+/// 查询 PID 不小于 `pid` 的第一个进程的资源使用，返回其 PID；没有更多进程时返回 `ESRCH`
+pub fn proc_usage(pid: usize, usage: &mut RUsage) -> isize {
+    unsafe { syscall2(SyscallId(2000), pid, usage as *mut _ as usize) }
+}
+
 pub fn get_time() -> isize {
     let mut time: TimeSpec = TimeSpec::ZERO;
     clock_gettime(ClockId::CLOCK_MONOTONIC, &mut time as *mut _ as _);
@@ -0,0 +1,64 @@
+//! 内存映射（对应 C 的 `<sys/mman.h>`）
+//!
+//! 与 `tg_syscall` 中第四章风格的 `mmap(start, len, prot)` 不同，这里的接口遵循 Linux 约定：
+//! mmap 成功时返回映射的起始地址，失败时返回负的错误码。
+
+use tg_syscall::{native::*, SyscallId};
+
+/// 页不可访问
+pub const PROT_NONE: usize = 0;
+/// 页可读
+pub const PROT_READ: usize = 1;
+/// 页可写（隐含可读）
+pub const PROT_WRITE: usize = 2;
+/// 页可执行
+pub const PROT_EXEC: usize = 4;
+
+/// 共享映射：修改经 msync、munmap 或进程结束写回文件
+pub const MAP_SHARED: usize = 0x01;
+/// 私有映射：修改不影响文件
+pub const MAP_PRIVATE: usize = 0x02;
+/// 在 `addr` 处映射，替换区间内已有的映射
+pub const MAP_FIXED: usize = 0x10;
+/// 匿名映射：不对应文件，内容为 0
+pub const MAP_ANONYMOUS: usize = 0x20;
+/// 在 `addr` 处映射，区间已被占用时失败
+pub const MAP_FIXED_NOREPLACE: usize = 0x10_0000;
+
+/// msync 的 `flags`：同步写回（内核总是同步写回）
+pub const MS_SYNC: usize = 4;
+
+/// mmap 系列的错误码
+pub use crate::{EACCES, EBADF, EEXIST, ENOMEM};
+
+/// mmap 系统调用：映射 `len` 字节，`addr` 为 0 时由内核选择地址；返回起始地址或负的错误码
+pub fn mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: isize,
+    offset: usize,
+) -> isize {
+    unsafe { syscall6(SyscallId(222), addr, len, prot, flags, fd as usize, offset) }
+}
+
+/// 建立 `len` 字节的匿名私有映射，返回起始地址或负的错误码
+pub fn mmap_anonymous(len: usize, prot: usize) -> isize {
+    mmap(0, len, prot, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0)
+}
+
+/// munmap 系统调用：取消 `[addr, addr + len)` 内的映射，`addr` 必须按页对齐
+pub fn munmap(addr: usize, len: usize) -> isize {
+    unsafe { syscall2(SyscallId(215), addr, len) }
+}
+
+/// mprotect 系统调用：把 `[addr, addr + len)` 的权限改为 `prot`，区间必须全部已映射
+pub fn mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    unsafe { syscall3(SyscallId(226), addr, len, prot) }
+}
+
+/// msync 系统调用：把 `[addr, addr + len)` 内共享文件映射的修改写回文件，区间必须全部已映射
+pub fn msync(addr: usize, len: usize, flags: usize) -> isize {
+    unsafe { syscall3(SyscallId(227), addr, len, flags) }
+}
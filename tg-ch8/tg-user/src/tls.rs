@@ -0,0 +1,78 @@
+//! 线程局部存储
+//!
+//! 用户库在 `.tbss` 中保留 `TLS_SLOTS` 个字，链接器据此生成 `PT_TLS` 段；
+//! 内核为每个线程分配一份 TLS 块并让 `tp` 指向它，因此每个线程看到的槽位互不影响。
+//!
+//! 稳定版 Rust 不支持 `#[thread_local]`，这里通过 `%tprel` 重定位取得槽位的地址，
+//! 对外提供类似 `pthread_key_create` 的 `TlsKey`。槽位 0 保留给 errno。
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// TLS 槽位数（与下方 `.zero` 的字节数对应）
+pub const TLS_SLOTS: usize = 32;
+
+core::arch::global_asm!(
+    ".pushsection .tbss.user_lib,\"awT\",@nobits",
+    ".p2align 3",
+    "__user_lib_tls:",
+    ".zero 256",
+    ".popsection",
+);
+
+/// 当前线程的 TLS 槽位数组
+#[inline]
+fn slots() -> *mut usize {
+    let ptr: *mut usize;
+    // SAFETY: 只根据 tp 计算地址，不访问内存
+    unsafe {
+        core::arch::asm!(
+            "lui {0}, %tprel_hi(__user_lib_tls)",
+            "add {0}, {0}, tp, %tprel_add(__user_lib_tls)",
+            "addi {0}, {0}, %tprel_lo(__user_lib_tls)",
+            out(reg) ptr,
+            options(pure, nomem, nostack),
+        );
+    }
+    ptr
+}
+
+/// 下一个未分配的槽位
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(1);
+
+/// 线程局部变量的键：每个线程各有一个 `usize` 值，初值为 0
+#[derive(Clone, Copy)]
+pub struct TlsKey(usize);
+
+impl TlsKey {
+    /// 分配一个新的键，槽位用尽时返回 `None`
+    pub fn new() -> Option<Self> {
+        let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        (key < TLS_SLOTS).then_some(Self(key))
+    }
+
+    /// 读取当前线程的值
+    #[inline]
+    pub fn get(self) -> usize {
+        // SAFETY: 槽位在当前线程的 TLS 块内，只有当前线程访问
+        unsafe { slots().add(self.0).read() }
+    }
+
+    /// 设置当前线程的值
+    #[inline]
+    pub fn set(self, value: usize) {
+        // SAFETY: 同上
+        unsafe { slots().add(self.0).write(value) }
+    }
+}
+
+/// 当前线程的 errno
+#[inline]
+pub fn errno() -> isize {
+    TlsKey(0).get() as isize
+}
+
+/// 设置当前线程的 errno
+#[inline]
+pub fn set_errno(value: isize) {
+    TlsKey(0).set(value as usize)
+}
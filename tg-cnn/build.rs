@@ -16,11 +16,14 @@ struct Cases {
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-env-changed=LOG");
+    println!("cargo:rerun-if-env-changed=TIME_SLICE");
     println!("cargo:rerun-if-env-changed=TG_USER_DIR");
     println!("cargo:rerun-if-env-changed=TG_USER_VERSION");
     println!("cargo:rerun-if-env-changed=TG_SKIP_USER_APPS");
     println!("cargo:rerun-if-env-changed=CARGO_FEATURE_EXERCISE");
 
+    write_time_slice();
+
     let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
 
     // 只在 RISC-V64 架构上使用链接脚本
@@ -33,6 +36,25 @@ fn main() {
     }
 }
 
+/// 生成时间片长度常量，默认 125000 个周期（约 10 ms），可通过环境变量 `TIME_SLICE` 覆盖
+fn write_time_slice() {
+    let time_slice = match env::var("TIME_SLICE") {
+        Ok(s) => match s.parse::<u64>() {
+            Ok(value) if value > 0 => value,
+            _ => panic!("TIME_SLICE must be a positive decimal integer, got {s:?}"),
+        },
+        Err(_) => 125000,
+    };
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("time_slice.rs");
+    let source = format!(
+        "/// 时间片长度（时钟周期数），构建时可通过环境变量 `TIME_SLICE` 覆盖\n\
+         const TIME_SLICE: u64 = {time_slice};\n"
+    );
+    fs::write(&path, source).unwrap_or_else(|err| {
+        panic!("failed to write {}: {}", path.display(), err)
+    });
+}
+
 fn should_skip_build_apps() -> bool {
     if env::var_os("TG_SKIP_USER_APPS").is_some() {
         return true;
@@ -188,6 +188,8 @@ const MEMORY: usize = 256 << 20;
 static mut HEAP_META: [u8; 4 * 1024 * 1024] = [1u8; 4 * 1024 * 1024];
 /// 异界传送门所在虚页
 const PROTAL_TRANSIT: VPN<Sv39> = VPN::MAX;
+// 时间片长度 `TIME_SLICE`（时钟周期数）由 build.rs 根据环境变量生成
+include!(concat!(env!("OUT_DIR"), "/time_slice.rs"));
 
 /// 内核地址空间的全局存储
 struct KernelSpace {
@@ -226,6 +228,7 @@ pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x00_8000)];
 /// - 使用 `PThreadManager`（双层管理器）替代 `PManager`
 /// - 初始化时同时创建 Process 和 Thread
 /// - 主循环中新增**线程阻塞**处理（SEMAPHORE_DOWN/MUTEX_LOCK/CONDVAR_WAIT）
+/// - 主循环使用时钟中断实现**抢占式**线程调度（时间片长度见 `TIME_SLICE`）
 extern "C" fn rust_main() -> ! {
     let layout = tg_linker::KernelLayout::locate();
     // 步骤 1：BSS 清零
@@ -330,6 +333,10 @@ extern "C" fn rust_main() -> ! {
         PROCESSOR.get_mut().add(tid, thread, pid);
     }
 
+    // 开启 S 特权级时钟中断：用户线程即使不发起系统调用，时间片耗尽后也会被抢占。
+    // 不开启 sstatus::SIE，内核态执行期间不响应中断，只在 U 态被打断。
+    unsafe { sie::set_stimer() };
+
     // ─── 主调度循环 ───
     loop {
         let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
@@ -349,9 +356,16 @@ extern "C" fn rust_main() -> ! {
 
 
         if let Some(task) = unsafe { (*processor).find_next() } {
+            // 设置本次时间片的截止时刻
+            tg_sbi::set_timer(time::read() as u64 + TIME_SLICE);
             unsafe { task.context.execute(portal, ()) };
 
             match scause::read().cause() {
+                // ─── 时钟中断：时间片用完，抢占当前线程 ───
+                scause::Trap::Interrupt(scause::Interrupt::SupervisorTimer) => {
+                    tg_sbi::set_timer(u64::MAX);
+                    unsafe { (*processor).make_current_suspend() };
+                }
                 // ─── 系统调用 ───
                 scause::Trap::Exception(scause::Exception::UserEnvCall) => {
                     use tg_syscall::{SyscallId as Id, SyscallResult as Ret};